@@ -2,7 +2,7 @@ use std::env::current_dir;
 use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
-use Vesting::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, VestingParameter, UserInfo};
+use Vesting::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, VestingParameter, UserInfo};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -12,6 +12,7 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(VestingParameter), &out_dir);
     export_schema(&schema_for!(UserInfo), &out_dir);
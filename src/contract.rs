@@ -2,15 +2,23 @@
 use cosmwasm_std::entry_point;
 
 use cosmwasm_std::{
-    Addr, to_binary, DepsMut, Env, MessageInfo, Response,
-    Uint128, CosmosMsg, WasmMsg, Storage
+    Addr, to_binary, from_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response,
+    Uint128, CosmosMsg, WasmMsg, BankMsg, Coin, Storage, QuerierWrapper, StdResult, Decimal, Order, Api
 };
+use std::collections::BTreeMap;
+
 use cw2::set_contract_version;
-use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, BalanceResponse as Cw20BalanceResponse, TokenInfoResponse};
+use cw_storage_plus::U128Key;
+use cw20::{Expiration, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse, BalanceResponse as Cw20BalanceResponse};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, ProjectInfo, UserInfo, VestingParameter, Config};
-use crate::state::{PROJECT_INFOS, OWNER};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, ProjectInfo, UserInfo, VestingParameter, Config, UserAllocation,
+    StageStats, AmountUnits, ProjectStatus, LaunchpadConfig, ProjectFee, SaleConfig, Sale, SaleStatus, AllowlistConfig, Operation,
+    Committee, Proposal, Position, NftApproval, Cw721ReceiveMsg,
+    RegistryQueryMsg, IsAllowedResponse, ReceiveMsg,
+    Milestone, MilestoneStatus, MilestoneVote, VotingConfig, PenaltyDestination, EarlyUnlock, ClaimGrant};
+use crate::state::{PROJECT_INFOS, ESCROWS, NEXT_PROJECT_ID, DEFAULT_LOCKED_TOKEN, VOTING_POWER, TOTAL_VOTING_POWER, OWNER, LAUNCHPAD, SALES, PURCHASES, CONTRIBUTIONS, ALLOWLIST, FROZEN_USERS,
+    OPERATIONS, NEXT_OPERATION_ID, PROPOSALS, NEXT_PROPOSAL_ID, POSITIONS, NEXT_TOKEN_ID, NFT_OPERATORS, MILESTONE_POWER, MILESTONE_VOTES, CLAIM_GRANTS, DISTRIBUTE_CURSORS};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "Vesting";
@@ -35,8 +43,230 @@ pub fn instantiate(
         .add_attribute("method", "instantiate"))
 }
 
+//projects created before escrows existed get what they still owe credited, in id order while
+//the contract holds enough of their token. anything beyond that has to be deposited again
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let projects: Vec<ProjectInfo> = PROJECT_INFOS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, x)| x))
+        .collect::<StdResult<_>>()?;
+
+    //token -> balance no escrow accounts for yet
+    let mut available: Vec<(String, Uint128)> = Vec::new();
+    let mut unfunded: Vec<&ProjectInfo> = Vec::new();
+    for x in projects.iter() {
+        if !available.iter().any(|(token, _)| *token == x.config.token_addr) {
+            let balance = query_token_balance(&deps.querier, &x.config.token_addr, &_env.contract.address)?;
+            available.push((x.config.token_addr.clone(), balance));
+        }
+        match ESCROWS.may_load(deps.storage, x.project_id.u128().into())? {
+            Some(escrow) => {
+                let (_, balance) = available.iter_mut().find(|(token, _)| *token == x.config.token_addr).unwrap();
+                *balance = balance.saturating_sub(escrow);
+            }
+            None => unfunded.push(x),
+        }
+    }
+
+    let mut credited = 0u64;
+    for x in unfunded {
+        let obligation = calc_obligation(&deps.querier, x)?;
+        let (_, balance) = available.iter_mut().find(|(token, _)| *token == x.config.token_addr).unwrap();
+        let escrow = std::cmp::min(obligation, *balance);
+        *balance = balance.checked_sub(escrow)?;
+        ESCROWS.save(deps.storage, x.project_id.u128().into(), &escrow)?;
+        credited += 1;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("credited", credited.to_string()))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
+    mut deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    check_project_status(deps.as_ref(), &info.sender, &msg)?;
+    check_timelock(deps.as_ref(), &msg)?;
+
+    //nft transfers move the voting power of the position's project
+    let moved = match &msg {
+        ExecuteMsg::TransferNft{ token_id, .. } | ExecuteMsg::SendNft{ token_id, .. }
+            => POSITIONS.may_load(deps.storage, token_id)?.map(|position| (token_id.clone(), position)),
+        _ => None,
+    };
+    let project_id = match &moved {
+        Some((_, position)) => Some(position.project_id),
+        None => target_project(&msg),
+    };
+    let before = match project_id {
+        Some(project_id) => voting_entries(deps.storage, project_id)?,
+        None => None,
+    };
+
+    let res = dispatch(deps.branch(), _env.clone(), info, msg)?;
+    if let (Some(project_id), Some(before)) = (project_id, before) {
+        let moved = moved.as_ref().map(|(token_id, position)| (token_id.as_str(), &position.owner));
+        sync_voting_power(deps.storage, &_env, project_id, &before, moved)?;
+    }
+    Ok(res)
+}
+
+//unreleased amount of every entry of a project, keyed by stage, position and wallet
+pub type VotingEntries = BTreeMap<(usize, Option<String>, Addr), Uint128>;
+
+//None when the project has no locked token, its holders aren't tracked then
+pub fn voting_entries(store: &dyn Storage, project_id: Uint128)
+    -> Result<Option<VotingEntries>, ContractError>
+{
+    let x = match PROJECT_INFOS.may_load(store, project_id.u128().into())? {
+        Some(x) if x.config.locked_token => x,
+        _ => return Ok(None),
+    };
+    let mut entries = VotingEntries::new();
+    for (stage, users) in x.users.iter().enumerate() {
+        for user in users.iter() {
+            let power = user.total_amount.saturating_sub(user.released_amount);
+            entries.insert((stage, user.token_id.clone(), user.wallet_address.clone()), power);
+        }
+    }
+    Ok(Some(entries))
+}
+
+//moves the power of the entries a message changed, every other holder keeps its snapshot
+pub fn sync_voting_power(store: &mut dyn Storage, _env: &Env, project_id: Uint128,
+    before: &VotingEntries, moved: Option<(&str, &Addr)>) -> Result<(), ContractError>
+{
+    let after = match voting_entries(store, project_id)? {
+        Some(after) => after,
+        None => return Ok(()),
+    };
+
+    //holder, power lost, power gained
+    let mut deltas: Vec<(Addr, Uint128, Uint128)> = Vec::new();
+    let mut add_delta = |holder: Addr, lost: Uint128, gained: Uint128| -> StdResult<()> {
+        match deltas.iter_mut().find(|(wallet, _, _)| *wallet == holder) {
+            Some((_, old_lost, old_gained)) => {
+                *old_lost = old_lost.checked_add(lost)?;
+                *old_gained = old_gained.checked_add(gained)?;
+            }
+            None => deltas.push((holder, lost, gained)),
+        }
+        Ok(())
+    };
+
+    let mut changes = Vec::new();
+    for (key, old) in before.iter() {
+        let new = after.get(key).copied().unwrap_or_default();
+        if new != *old {
+            changes.push((key, *old, new));
+        }
+    }
+    for (key, new) in after.iter() {
+        if !before.contains_key(key) && !new.is_zero() {
+            changes.push((key, Uint128::zero(), *new));
+        }
+    }
+    for ((_, token_id, wallet), old, new) in changes {
+        let holder = match token_id {
+            Some(token_id) => POSITIONS.load(store, token_id)?.owner,
+            None => wallet.clone(),
+        };
+        add_delta(holder, old, new)?;
+    }
+
+    if let Some((token_id, from)) = moved {
+        let to = POSITIONS.load(store, token_id)?.owner;
+        let power = after.iter()
+            .find(|((_, id, _), _)| id.as_deref() == Some(token_id))
+            .map(|(_, power)| *power)
+            .unwrap_or_default();
+        add_delta(from.clone(), power, Uint128::zero())?;
+        add_delta(to, Uint128::zero(), power)?;
+    }
+
+    //a snapshot that lags behind only ever reads low, it never blocks the message that moved the tokens
+    let height = _env.block.height;
+    let old_total = TOTAL_VOTING_POWER.may_load(store, project_id.u128().into())?.unwrap_or_default();
+    let mut total = old_total;
+    for (holder, lost, gained) in deltas {
+        if lost == gained {
+            continue;
+        }
+        let key = (project_id.u128().into(), &holder);
+        let power = VOTING_POWER.may_load(store, key.clone())?.unwrap_or_default()
+            .checked_add(gained)?.saturating_sub(lost);
+        total = total.checked_add(gained)?.saturating_sub(lost);
+        if power.is_zero() {
+            VOTING_POWER.remove(store, key, height)?;
+        } else {
+            VOTING_POWER.save(store, key, &power, height)?;
+        }
+    }
+    if old_total != total {
+        TOTAL_VOTING_POWER.save(store, project_id.u128().into(), &total, height)?;
+    }
+    Ok(())
+}
+
+//rebuilds every holder of a project, only when its locked token gets enabled
+pub fn resync_voting_power(store: &mut dyn Storage, _env: &Env, project_id: Uint128)
+    -> Result<(), ContractError>
+{
+    let height = _env.block.height;
+    let mut powers: Vec<(Addr, Uint128)> = Vec::new();
+    if let Some(x) = PROJECT_INFOS.may_load(store, project_id.u128().into())? {
+        for user in x.users.iter().flatten() {
+            let holder = match &user.token_id {
+                Some(token_id) => POSITIONS.load(store, token_id)?.owner,
+                None => user.wallet_address.clone(),
+            };
+            let power = user.total_amount.saturating_sub(user.released_amount);
+            match powers.iter_mut().find(|(wallet, _)| *wallet == holder) {
+                Some((_, total)) => *total = total.checked_add(power)?,
+                None => powers.push((holder, power)),
+            }
+        }
+    }
+
+    let stored: Vec<(Addr, Uint128)> = VOTING_POWER
+        .prefix(project_id.u128().into())
+        .range(store, None, None, Order::Ascending)
+        .map(|item| item.map(|(wallet, power)| (Addr::unchecked(String::from_utf8_lossy(&wallet)), power)))
+        .collect::<StdResult<_>>()?;
+    for (wallet, _) in stored.iter() {
+        if !powers.iter().any(|(holder, power)| holder == wallet && !power.is_zero()) {
+            VOTING_POWER.remove(store, (project_id.u128().into(), wallet), height)?;
+        }
+    }
+
+    let mut total = Uint128::zero();
+    for (holder, power) in powers.iter() {
+        total = total.checked_add(*power)?;
+        if power.is_zero() {
+            continue;
+        }
+        if !stored.iter().any(|(wallet, old)| wallet == holder && old == power) {
+            VOTING_POWER.save(store, (project_id.u128().into(), holder), power, height)?;
+        }
+    }
+
+    let old_total = TOTAL_VOTING_POWER.may_load(store, project_id.u128().into())?.unwrap_or_default();
+    if old_total != total {
+        TOTAL_VOTING_POWER.save(store, project_id.u128().into(), &total, height)?;
+    }
+    Ok(())
+}
+
+//runs a message without the entry checks, queued operations come back through here
+pub fn dispatch(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
@@ -47,19 +277,28 @@ pub fn execute(
             => try_setconfig(deps, info, admin),
 
         ExecuteMsg::StartRelease{ project_id, start_time }
-            => try_startrelease(deps, info, project_id, start_time),
+            => try_startrelease(deps, _env, info, project_id, start_time),
 
-        ExecuteMsg::AddProject{ project_id, admin, token_addr, vesting_params, start_time }
-            => try_addproject(deps, info, project_id, admin, token_addr, vesting_params, start_time ),
+        ExecuteMsg::AddProject{ project_id, admin, token_addr, vesting_params, start_time, units }
+            => try_addproject(deps, info, project_id, admin, token_addr, vesting_params, start_time, units ),
 
         ExecuteMsg::SetProjectInfo{ project_id, project_info }
-            => try_setprojectinfo(deps, info, project_id, project_info ),
+            => try_setprojectinfo(deps, _env, info, project_id, project_info ),
 
         ExecuteMsg::SetProjectConfig{ project_id, admin, token_addr , start_time} 
-            => try_setprojectconfig(deps, info, project_id, admin, token_addr, start_time),
+            => try_setprojectconfig(deps, _env, info, project_id, admin, token_addr, start_time),
 
         ExecuteMsg::SetVestingParameters{ project_id, params }
-            => try_setvestingparameters(deps, info, project_id, params),
+            => try_setvestingparameters(deps, _env, info, project_id, params),
+
+        ExecuteMsg::SetAllocation { project_id, stage, wallet, amount } 
+            =>  try_setallocation(deps, info, project_id, stage, wallet, amount),
+
+        ExecuteMsg::MoveAllocation { project_id, from_stage, to_stage, wallet, amount } 
+            =>  try_moveallocation(deps, _env, info, project_id, from_stage, to_stage, wallet, amount),
+
+        ExecuteMsg::RemoveUser { project_id, stage, wallet } 
+            =>  try_removeuser(deps, info, project_id, stage, wallet),
 
         ExecuteMsg::SetUsers { project_id, stage, user_infos } 
             =>  try_setusers(deps, info, project_id, stage, user_infos),
@@ -67,41 +306,1985 @@ pub fn execute(
         ExecuteMsg::AddUser { project_id, stage, wallet, amount } 
             =>  try_adduser(deps, info, project_id, stage, wallet, amount),
 
-        ExecuteMsg::ClaimPendingTokens { project_id, }
-            =>  try_claimpendingtokens(deps, _env, info, project_id )
+        ExecuteMsg::AddUsers { project_id, stage, users } 
+            =>  try_addusers(deps, info, project_id, stage, users),
+
+        ExecuteMsg::ClaimPendingTokens { project_id, beneficiary, recipient }
+            =>  try_claimpendingtokens(deps, _env, info, project_id, beneficiary, recipient ),
+
+        ExecuteMsg::ClaimAndSend { project_id, contract, msg, beneficiary }
+            =>  try_claimandsend(deps, _env, info, project_id, contract, msg, beneficiary),
+
+        ExecuteMsg::Distribute { project_id, stage, limit }
+            =>  try_distribute(deps, _env, info, project_id, stage, limit),
+
+        ExecuteMsg::SetSendTargets { project_id, targets }
+            =>  try_setsendtargets(deps, info, project_id, targets),
+
+        ExecuteMsg::GrantClaim { project_id, grantee, allow_recipient }
+            =>  try_grantclaim(deps, info, project_id, grantee, allow_recipient),
+
+        ExecuteMsg::RevokeClaimGrant { project_id, grantee }
+            =>  try_revokeclaimgrant(deps, info, project_id, grantee),
+
+        ExecuteMsg::EarlyClaim { project_id }
+            =>  try_earlyclaim(deps, _env, info, project_id),
+
+        ExecuteMsg::AddMilestone { project_id, stage, name, percent, period }
+            =>  try_addmilestone(deps, _env, info, project_id, stage, name, percent, period),
+
+        ExecuteMsg::ApproveMilestone { project_id, name }
+            =>  try_decidemilestone(deps, _env, info, project_id, name, MilestoneStatus::Approved),
+
+        ExecuteMsg::RejectMilestone { project_id, name }
+            =>  try_decidemilestone(deps, _env, info, project_id, name, MilestoneStatus::Rejected),
+
+        ExecuteMsg::SetMilestoneVoting { project_id, voting }
+            =>  try_setmilestonevoting(deps, info, project_id, voting),
+
+        ExecuteMsg::StartMilestoneVote { project_id, name }
+            =>  try_startmilestonevote(deps, _env, info, project_id, name),
+
+        ExecuteMsg::VoteMilestone { project_id, name, approve }
+            =>  try_votemilestone(deps, _env, info, project_id, name, approve),
+
+        ExecuteMsg::TallyMilestoneVote { project_id, name }
+            =>  try_tallymilestonevote(deps, _env, project_id, name),
+
+        ExecuteMsg::SetLaunchpad { launchpad }
+            =>  try_setlaunchpad(deps, info, launchpad),
+
+        ExecuteMsg::SetProjectStatus { project_id, status }
+            =>  try_setprojectstatus(deps, info, project_id, status),
+
+        ExecuteMsg::SetSale { project_id, stage, sale }
+            =>  try_setsale(deps, info, project_id, stage, sale),
+
+        ExecuteMsg::Purchase { project_id, stage }
+            =>  try_purchase(deps, _env, info, project_id, stage),
+
+        ExecuteMsg::Refund { project_id, stage }
+            =>  try_refund(deps, _env, info, project_id, stage),
+
+        ExecuteMsg::WithdrawRaised { project_id, stage }
+            =>  try_withdrawraised(deps, _env, info, project_id, stage),
+
+        ExecuteMsg::SetAllowlist { project_id, allowlist }
+            =>  try_setallowlist(deps, info, project_id, allowlist),
+
+        ExecuteMsg::UpdateAllowlist { project_id, add, remove }
+            =>  try_updateallowlist(deps, info, project_id, add, remove),
+
+        ExecuteMsg::FreezeUser { project_id, wallet }
+            =>  try_setfrozen(deps, info, project_id, wallet, true),
+
+        ExecuteMsg::UnfreezeUser { project_id, wallet }
+            =>  try_setfrozen(deps, info, project_id, wallet, false),
+
+        ExecuteMsg::Clawback { project_id, stage, wallet, recipient }
+            =>  try_clawback(deps, _env, info, project_id, stage, wallet, recipient),
+
+        ExecuteMsg::SetTimelock { project_id, delay }
+            =>  try_settimelock(deps, info, project_id, delay),
+
+        ExecuteMsg::QueueOperation { project_id, msg }
+            =>  try_queueoperation(deps, _env, info, project_id, *msg),
+
+        ExecuteMsg::ExecuteOperation { project_id, operation_id }
+            =>  try_executeoperation(deps, _env, project_id, operation_id),
+
+        ExecuteMsg::CancelOperation { project_id, operation_id }
+            =>  try_canceloperation(deps, info, project_id, operation_id),
+
+        ExecuteMsg::SetCommittee { project_id, committee }
+            =>  try_setcommittee(deps, _env, info, project_id, committee),
+
+        ExecuteMsg::Propose { project_id, msg }
+            =>  try_propose(deps, _env, info, project_id, *msg),
+
+        ExecuteMsg::ApproveProposal { project_id, proposal_id }
+            =>  try_approveproposal(deps, _env, info, project_id, proposal_id),
+
+        ExecuteMsg::ExecuteProposal { project_id, proposal_id }
+            =>  try_executeproposal(deps, _env, project_id, proposal_id),
+
+        ExecuteMsg::SetLockedToken { project_id, enabled }
+            =>  try_setlockedtoken(deps, _env, info, project_id, enabled),
+
+        ExecuteMsg::SetDefaultLockedToken { project_id }
+            =>  try_setdefaultlockedtoken(deps, info, project_id),
+
+        ExecuteMsg::Receive(wrapper)
+            =>  try_receive(deps, info, wrapper),
+
+        ExecuteMsg::WithdrawEscrow{ project_id, amount, recipient }
+            =>  try_withdrawescrow(deps, info, project_id, amount, recipient),
+
+        ExecuteMsg::Transfer{ .. }
+        | ExecuteMsg::Send{ .. }
+        | ExecuteMsg::IncreaseAllowance{ .. }
+        | ExecuteMsg::DecreaseAllowance{ .. }
+        | ExecuteMsg::TransferFrom{ .. }
+        | ExecuteMsg::SendFrom{ .. }
+            =>  Err(ContractError::LockedTokenNotTransferable{ }),
+
+        ExecuteMsg::Tokenize { project_id, stage }
+            =>  try_tokenize(deps, info, project_id, stage),
+
+        ExecuteMsg::ClaimPosition { project_id, token_id, recipient }
+            =>  try_claimposition(deps, _env, info, project_id, token_id, recipient),
+
+        ExecuteMsg::TransferNft { recipient, token_id }
+            =>  try_transfernft(deps, _env, info, recipient, token_id),
+
+        ExecuteMsg::SendNft { contract, token_id, msg }
+            =>  try_sendnft(deps, _env, info, contract, token_id, msg),
+
+        ExecuteMsg::Approve { spender, token_id, expires }
+            =>  try_approvenft(deps, _env, info, spender, token_id, expires),
+
+        ExecuteMsg::Revoke { spender, token_id }
+            =>  try_revokenft(deps, _env, info, spender, token_id),
+
+        ExecuteMsg::ApproveAll { operator, expires }
+            =>  try_approveall(deps, _env, info, operator, expires),
+
+        ExecuteMsg::RevokeAll { operator }
+            =>  try_revokeall(deps, info, operator)
+    }
+}
+
+//the project a message acts on, None for contract wide messages
+pub fn target_project(msg: &ExecuteMsg) -> Option<Uint128>
+{
+    match msg {
+        ExecuteMsg::SetConfig{ .. }
+        | ExecuteMsg::AddProject{ .. }
+        | ExecuteMsg::SetLaunchpad{ .. }
+        | ExecuteMsg::SetDefaultLockedToken{ .. }
+        | ExecuteMsg::Receive(..)
+        | ExecuteMsg::Transfer{ .. }
+        | ExecuteMsg::Send{ .. }
+        | ExecuteMsg::IncreaseAllowance{ .. }
+        | ExecuteMsg::DecreaseAllowance{ .. }
+        | ExecuteMsg::TransferFrom{ .. }
+        | ExecuteMsg::SendFrom{ .. }
+        | ExecuteMsg::TransferNft{ .. }
+        | ExecuteMsg::SendNft{ .. }
+        | ExecuteMsg::Approve{ .. }
+        | ExecuteMsg::Revoke{ .. }
+        | ExecuteMsg::ApproveAll{ .. }
+        | ExecuteMsg::RevokeAll{ .. } => None,
+
+        ExecuteMsg::StartRelease{ project_id, .. }
+        | ExecuteMsg::SetProjectInfo{ project_id, .. }
+        | ExecuteMsg::SetProjectConfig{ project_id, .. }
+        | ExecuteMsg::SetVestingParameters{ project_id, .. }
+        | ExecuteMsg::AddUser{ project_id, .. }
+        | ExecuteMsg::AddUsers{ project_id, .. }
+        | ExecuteMsg::SetAllocation{ project_id, .. }
+        | ExecuteMsg::MoveAllocation{ project_id, .. }
+        | ExecuteMsg::RemoveUser{ project_id, .. }
+        | ExecuteMsg::SetUsers{ project_id, .. }
+        | ExecuteMsg::ClaimPendingTokens{ project_id, .. }
+        | ExecuteMsg::ClaimAndSend{ project_id, .. }
+        | ExecuteMsg::Distribute{ project_id, .. }
+        | ExecuteMsg::SetSendTargets{ project_id, .. }
+        | ExecuteMsg::GrantClaim{ project_id, .. }
+        | ExecuteMsg::RevokeClaimGrant{ project_id, .. }
+        | ExecuteMsg::EarlyClaim{ project_id }
+        | ExecuteMsg::AddMilestone{ project_id, .. }
+        | ExecuteMsg::ApproveMilestone{ project_id, .. }
+        | ExecuteMsg::RejectMilestone{ project_id, .. }
+        | ExecuteMsg::SetMilestoneVoting{ project_id, .. }
+        | ExecuteMsg::StartMilestoneVote{ project_id, .. }
+        | ExecuteMsg::VoteMilestone{ project_id, .. }
+        | ExecuteMsg::TallyMilestoneVote{ project_id, .. }
+        | ExecuteMsg::SetProjectStatus{ project_id, .. }
+        | ExecuteMsg::SetSale{ project_id, .. }
+        | ExecuteMsg::Purchase{ project_id, .. }
+        | ExecuteMsg::Refund{ project_id, .. }
+        | ExecuteMsg::WithdrawRaised{ project_id, .. }
+        | ExecuteMsg::SetAllowlist{ project_id, .. }
+        | ExecuteMsg::UpdateAllowlist{ project_id, .. }
+        | ExecuteMsg::FreezeUser{ project_id, .. }
+        | ExecuteMsg::UnfreezeUser{ project_id, .. }
+        | ExecuteMsg::Clawback{ project_id, .. }
+        | ExecuteMsg::SetTimelock{ project_id, .. }
+        | ExecuteMsg::QueueOperation{ project_id, .. }
+        | ExecuteMsg::ExecuteOperation{ project_id, .. }
+        | ExecuteMsg::CancelOperation{ project_id, .. }
+        | ExecuteMsg::SetCommittee{ project_id, .. }
+        | ExecuteMsg::Propose{ project_id, .. }
+        | ExecuteMsg::ApproveProposal{ project_id, .. }
+        | ExecuteMsg::ExecuteProposal{ project_id, .. }
+        | ExecuteMsg::SetLockedToken{ project_id, .. }
+        | ExecuteMsg::Tokenize{ project_id, .. }
+        | ExecuteMsg::ClaimPosition{ project_id, .. }
+        | ExecuteMsg::WithdrawEscrow{ project_id, .. } => Some(*project_id),
+    }
+}
+
+//operations that can take tokens away from investors, lower or move allocations,
+//keep wallets from claiming through the allowlist or send targets, or change who controls the project.
+//FreezeUser stays immediate on purpose: it only holds claims during a dispute, nothing is taken,
+//and a freeze that waits out the timelock would let the disputed tokens leave first
+pub fn is_sensitive(msg: &ExecuteMsg, config: &Config) -> bool
+{
+    match msg {
+        ExecuteMsg::SetProjectInfo{ .. }
+        | ExecuteMsg::SetProjectConfig{ .. }
+        | ExecuteMsg::SetVestingParameters{ .. }
+        | ExecuteMsg::Clawback{ .. }
+        | ExecuteMsg::SetAllocation{ .. }
+        | ExecuteMsg::RemoveUser{ .. }
+        | ExecuteMsg::SetUsers{ .. }
+        | ExecuteMsg::MoveAllocation{ .. }
+        | ExecuteMsg::SetAllowlist{ .. }
+        | ExecuteMsg::UpdateAllowlist{ .. }
+        | ExecuteMsg::SetSendTargets{ .. }
+        | ExecuteMsg::SetCommittee{ .. } => true,
+        ExecuteMsg::SetTimelock{ delay, .. } => *delay < config.timelock,
+        _ => false,
+    }
+}
+
+//sensitive operations of a timelocked project only run through the queue
+pub fn check_timelock(deps: Deps, msg: &ExecuteMsg) -> Result<(), ContractError>
+{
+    let project_id = match target_project(msg) {
+        Some(project_id) => project_id,
+        None => return Ok(()),
+    };
+    if let Some(x) = PROJECT_INFOS.may_load(deps.storage, project_id.u128().into())? {
+        if x.config.timelock > Uint128::zero() && is_sensitive(msg, &x.config) {
+            return Err(ContractError::OperationTimelocked{ });
+        }
+    }
+    Ok(())
+}
+
+pub fn try_settimelock(deps: DepsMut, info: MessageInfo, project_id: Uint128, delay: Uint128)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    x.config.timelock = delay;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Set timelock")
+    .add_attribute("delay", delay))
+}
+
+pub fn try_queueoperation(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, msg: ExecuteMsg)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+    if target_project(&msg) != Some(project_id) || !is_sensitive(&msg, &x.config) {
+        return Err(ContractError::InvalidOperation{ });
+    }
+
+    let operation_id = NEXT_OPERATION_ID.may_load(deps.storage)?.unwrap_or_default();
+    NEXT_OPERATION_ID.save(deps.storage, &(operation_id + Uint128::new(1)))?;
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    let operation = Operation{
+        operation_id,
+        msg,
+        proposer: info.sender,
+        eta: now.checked_add(x.config.timelock)?,
+    };
+    OPERATIONS.save(deps.storage, (project_id.u128().into(), operation_id.u128().into()), &operation)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Queue operation")
+    .add_attribute("operation_id", operation_id)
+    .add_attribute("eta", operation.eta))
+}
+
+//anyone may push a ripe operation through, it runs as its proposer
+pub fn try_executeoperation(deps: DepsMut, _env: Env, project_id: Uint128, operation_id: Uint128)
+    ->Result<Response, ContractError>
+{
+    let key = (project_id.u128().into(), operation_id.u128().into());
+    let operation = OPERATIONS.may_load(deps.storage, key)?
+        .ok_or(ContractError::OperationNotFound{ operation_id })?;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if now < operation.eta {
+        return Err(ContractError::OperationNotReady{ operation_id });
+    }
+    OPERATIONS.remove(deps.storage, (project_id.u128().into(), operation_id.u128().into()));
+
+    let info = MessageInfo{ sender: operation.proposer, funds: Vec::new() };
+    let res = dispatch(deps, _env, info, operation.msg)?;
+    Ok(res.add_attribute("operation_id", operation_id))
+}
+
+pub fn try_canceloperation(deps: DepsMut, info: MessageInfo, project_id: Uint128, operation_id: Uint128)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let key = (project_id.u128().into(), operation_id.u128().into());
+    if !OPERATIONS.has(deps.storage, key) {
+        return Err(ContractError::OperationNotFound{ operation_id });
+    }
+    OPERATIONS.remove(deps.storage, (project_id.u128().into(), operation_id.u128().into()));
+
+    Ok(Response::new()
+    .add_attribute("action", "Cancel operation")
+    .add_attribute("operation_id", operation_id))
+}
+
+//a committee takes the project over, the contract itself becomes the admin and acts on passed proposals
+pub fn try_setcommittee(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, committee: Option<Committee>)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let committee = match committee {
+        //a member listed twice still approves once
+        Some(committee) => {
+            let mut members = committee.members.iter()
+                .map(|member| deps.api.addr_validate(member.as_str()))
+                .collect::<StdResult<Vec<Addr>>>()?;
+            members.sort();
+            members.dedup();
+            if committee.threshold == 0 || committee.threshold as usize > members.len() {
+                return Err(ContractError::InvalidCommittee{ });
+            }
+            x.config.owner = _env.contract.address;
+            Some(Committee{ members, ..committee })
+        }
+        //hand the project to a wallet through SetProjectConfig before dissolving the committee
+        None => {
+            if x.config.owner == _env.contract.address {
+                return Err(ContractError::InvalidCommittee{ });
+            }
+            None
+        }
+    };
+    x.config.committee = committee;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Set committee"))
+}
+
+pub fn check_member(x: &ProjectInfo, sender: &Addr) -> Result<Committee, ContractError>
+{
+    match &x.config.committee {
+        Some(committee) if committee.members.contains(sender) => Ok(committee.clone()),
+        _ => Err(ContractError::NotCommitteeMember{ }),
+    }
+}
+
+pub fn try_propose(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, msg: ExecuteMsg)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let committee = check_member(&x, &info.sender)?;
+    let nested = matches!(msg, ExecuteMsg::Propose{ .. } | ExecuteMsg::ApproveProposal{ .. } | ExecuteMsg::ExecuteProposal{ .. });
+    if nested || target_project(&msg) != Some(project_id) {
+        return Err(ContractError::InvalidOperation{ });
+    }
+
+    let proposal_id = NEXT_PROPOSAL_ID.may_load(deps.storage)?.unwrap_or_default();
+    NEXT_PROPOSAL_ID.save(deps.storage, &(proposal_id + Uint128::new(1)))?;
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    let proposal = Proposal{
+        proposal_id,
+        msg,
+        proposer: info.sender.clone(),
+        approvals: vec![info.sender],
+        expires: now.checked_add(committee.expiry)?,
+        executed: false,
+    };
+    PROPOSALS.save(deps.storage, (project_id.u128().into(), proposal_id.u128().into()), &proposal)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Propose")
+    .add_attribute("proposal_id", proposal_id))
+}
+
+pub fn load_open_proposal(store: &dyn Storage, _env: &Env, project_id: Uint128, proposal_id: Uint128)
+    -> Result<Proposal, ContractError>
+{
+    let proposal = PROPOSALS.may_load(store, (project_id.u128().into(), proposal_id.u128().into()))?
+        .ok_or(ContractError::ProposalNotFound{ proposal_id })?;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if proposal.executed || now >= proposal.expires {
+        return Err(ContractError::ProposalClosed{ proposal_id });
+    }
+    Ok(proposal)
+}
+
+pub fn try_approveproposal(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, proposal_id: Uint128)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_member(&x, &info.sender)?;
+    let mut proposal = load_open_proposal(deps.storage, &_env, project_id, proposal_id)?;
+    if proposal.approvals.contains(&info.sender) {
+        return Err(ContractError::AlreadyApproved{ proposal_id });
+    }
+    proposal.approvals.push(info.sender);
+    PROPOSALS.save(deps.storage, (project_id.u128().into(), proposal_id.u128().into()), &proposal)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Approve proposal")
+    .add_attribute("proposal_id", proposal_id)
+    .add_attribute("approvals", proposal.approvals.len().to_string()))
+}
+
+//anyone may run a passed proposal, it goes through the same checks as a message of the admin
+pub fn try_executeproposal(deps: DepsMut, _env: Env, project_id: Uint128, proposal_id: Uint128)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let mut proposal = load_open_proposal(deps.storage, &_env, project_id, proposal_id)?;
+
+    //only approvals of current members count
+    let committee = x.config.committee.ok_or(ContractError::NotCommitteeMember{ })?;
+    let approvals = proposal.approvals.iter().filter(|member| committee.members.contains(member)).count();
+    if approvals < committee.threshold as usize {
+        return Err(ContractError::ThresholdNotReached{ proposal_id });
+    }
+    proposal.executed = true;
+    PROPOSALS.save(deps.storage, (project_id.u128().into(), proposal_id.u128().into()), &proposal)?;
+
+    let info = MessageInfo{ sender: _env.contract.address.clone(), funds: Vec::new() };
+    check_project_status(deps.as_ref(), &info.sender, &proposal.msg)?;
+    check_timelock(deps.as_ref(), &proposal.msg)?;
+    let res = dispatch(deps, _env, info, proposal.msg)?;
+    Ok(res.add_attribute("proposal_id", proposal_id))
+}
+
+//paused and delisted projects only take messages from the owner
+pub fn check_project_status(deps: Deps, sender: &Addr, msg: &ExecuteMsg) -> Result<(), ContractError>
+{
+    let project_id = match target_project(msg) {
+        Some(project_id) => project_id,
+        None => return Ok(()),
+    };
+    if *sender == OWNER.load(deps.storage)? {
+        return Ok(());
+    }
+    if let Some(x) = PROJECT_INFOS.may_load(deps.storage, project_id.u128().into())? {
+        if x.config.status != ProjectStatus::Active {
+            return Err(ContractError::ProjectPaused{ project_id });
+        }
+    }
+    Ok(())
+}
+
+pub fn is_release_started(config: &Config, now: Uint128) -> bool
+{
+    config.start_time != Uint128::zero() && config.start_time <= now
+}
+
+//every stage needs an unlock at start of at most 100% and a non-zero release period
+pub fn validate_vesting_parameters(api: &dyn Api, params: &[VestingParameter]) -> Result<(), ContractError>
+{
+    if params.is_empty() {
+        return Err(ContractError::InvalidVestingParameter{ });
+    }
+    for param in params {
+        if param.soon > Uint128::new(100) || param.period == Uint128::zero() {
+            return Err(ContractError::InvalidVestingParameter{ });
+        }
+        //a bad treasury would make every early claim of the stage fail
+        if let Some(EarlyUnlock{ destination: PenaltyDestination::Treasury{ address }, .. }) = &param.early_unlock {
+            api.addr_validate(address)?;
+        }
     }
+    Ok(())
+}
+
+//once the release started the start can only move earlier, anything else takes back unlocked tokens
+pub fn check_start_time_change(config: &Config, start_time: Uint128, now: Uint128)
+    -> Result<(), ContractError>
+{
+    if is_release_started(config, now)
+        && (start_time == Uint128::zero() || start_time > config.start_time)
+    {
+        return Err(ContractError::StartTimeDelayed{ });
+    }
+    Ok(())
+}
+
+//once the release started the parameters can only change in favour of the investors
+pub fn check_vesting_parameters_change(config: &Config, old: &[VestingParameter], new: &[VestingParameter],
+    now: Uint128)
+    -> Result<(), ContractError>
+{
+    if !is_release_started(config, now) {
+        return Ok(());
+    }
+    if old.len() != new.len() {
+        return Err(ContractError::StageCountChanged{ });
+    }
+    for (old, new) in old.iter().zip(new.iter()) {
+        if new.soon < old.soon {
+            return Err(ContractError::ImmediateUnlockReduced{ });
+        }
+        if new.after > old.after {
+            return Err(ContractError::CliffExtended{ });
+        }
+        if new.period > old.period {
+            return Err(ContractError::PeriodExtended{ });
+        }
+        //adding or dropping the option is fine, a harsher one is not
+        if let (Some(old), Some(new)) = (&old.early_unlock, &new.early_unlock) {
+            if new.penalty > old.penalty || (old.decay && !new.decay) {
+                return Err(ContractError::PenaltyIncreased{ });
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn try_startrelease(deps: DepsMut, _env: Env, info:MessageInfo, project_id: Uint128, start_time: Uint128)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    check_start_time_change(&x.config, start_time, now)?;
+    x.config.start_time = start_time;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Start Release"))  
+}
+
+pub fn try_setprojectinfo(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, project_info: ProjectInfo)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    check_start_time_change(&x.config, project_info.config.start_time, now)?;
+    check_vesting_parameters_change(&x.config, &x.vest_param, &project_info.vest_param, now)?;
+    validate_vesting_parameters(deps.api, &project_info.vest_param)?;
+
+    //milestones and the token decide what the released allocations are worth
+    if is_release_started(&x.config, now)
+        && (project_info.milestones != x.milestones
+            || project_info.config.token_addr != x.config.token_addr
+            || project_info.config.units != x.config.units)
+    {
+        return Err(ContractError::ReleaseAlreadyStarted{ });
+    }
+    check_token_change(deps.storage, &x, &project_info.config.token_addr)?;
+
+    //the status is up to the owner alone, allocations and their accounting only change through their own messages.
+    //committee, timelock, send targets, allowlist and locked token are checked by their own messages as well,
+    //the locked token e.g. only turns on through SetLockedToken, which builds the voting power snapshots
+    let kept = x.config;
+    let users = x.users;
+    let reward_per_share = x.reward_per_share;
+    x = project_info;
+    x.config.status = kept.status;
+    x.config.committee = kept.committee;
+    x.config.timelock = kept.timelock;
+    x.config.send_targets = kept.send_targets;
+    x.config.allowlist = kept.allowlist;
+    x.config.locked_token = kept.locked_token;
+    x.users = users;
+    x.reward_per_share = reward_per_share;
+    //escrow, allowlist and the rest are looked up by the id, it has to stay the storage key
+    x.project_id = project_id;
+    resize_stages(&mut x)?;
+    x.config.decimals = Some(query_token_decimals(&deps.querier, &x.config.token_addr)?);
+    check_escrow(deps.storage, &deps.querier, &x)?;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "set Project Info"))    
+}
+pub fn try_setvestingparameters(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, params: Vec<VestingParameter>)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    check_vesting_parameters_change(&x.config, &x.vest_param, &params, now)?;
+    validate_vesting_parameters(deps.api, &params)?;
+
+    x.vest_param = params;
+    resize_stages(&mut x)?;
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Set Vesting parameters"))
+}
+
+//one allocation list per vesting stage, a stage only goes away while nothing is allocated or gated in it
+pub fn resize_stages(x: &mut ProjectInfo) -> Result<(), ContractError>
+{
+    let stages = x.vest_param.len();
+    for stage in stages..x.users.len() {
+        if !x.users[stage].is_empty() || x.milestones.iter().any(|m| m.stage.u128() as usize == stage) {
+            return Err(ContractError::StageNotEmpty{ stage: Uint128::new(stage as u128) });
+        }
+    }
+    x.users.resize(stages, Vec::new());
+    x.reward_per_share.truncate(stages);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_addmilestone(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    stage: Uint128, name: String, percent: Uint128, period: Uint128)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    //gating a share after release would take back tokens that are already unlocked
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if is_release_started(&x.config, now) {
+        return Err(ContractError::ReleaseAlreadyStarted{ });
+    }
+
+    if x.milestones.iter().any(|m| m.name == name) {
+        return Err(ContractError::MilestoneExists{ name });
+    }
+    if stage.u128() as usize >= x.vest_param.len() {
+        return Err(ContractError::InvalidStage{ stage });
+    }
+
+    let gated = x.milestones.iter()
+        .filter(|m| m.stage == stage)
+        .fold(percent, |sum, m| sum + m.percent);
+    if gated > Uint128::new(100) {
+        return Err(ContractError::InvalidMilestonePercent{ });
+    }
+
+    x.milestones.push(Milestone{
+        name: name.clone(),
+        stage,
+        percent,
+        period,
+        status: MilestoneStatus::Pending,
+        approved_at: Uint128::zero(),
+        vote: None,
+    });
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Add milestone")
+    .add_attribute("name", name))
+}
+
+pub fn try_decidemilestone(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    name: String, status: MilestoneStatus)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+    if x.milestone_voting.is_some() {
+        return Err(ContractError::MilestoneVotingEnabled{ });
+    }
+
+    let milestone = x.milestones.iter_mut().find(|m| m.name == name)
+        .ok_or(ContractError::MilestoneNotFound{ name: name.clone() })?;
+    if milestone.status != MilestoneStatus::Pending {
+        return Err(ContractError::MilestoneAlreadyDecided{ name });
+    }
+
+    if status == MilestoneStatus::Approved {
+        milestone.approved_at = Uint128::new(_env.block.time.seconds() as u128);
+    }
+    milestone.status = status;
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Decide milestone")
+    .add_attribute("name", name))
+}
+
+pub fn try_setmilestonevoting(deps: DepsMut, info: MessageInfo, project_id: Uint128,
+    voting: Option<VotingConfig>)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    x.milestone_voting = voting;
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Set milestone voting"))
+}
+
+pub fn try_startmilestonevote(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    name: String)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+    let voting = x.milestone_voting.clone().ok_or(ContractError::MilestoneVotingDisabled{ })?;
+
+    let milestone = x.milestones.iter().find(|m| m.name == name)
+        .ok_or(ContractError::MilestoneNotFound{ name: name.clone() })?;
+    if milestone.status != MilestoneStatus::Pending || milestone.vote.is_some() {
+        return Err(ContractError::MilestoneAlreadyDecided{ name });
+    }
+
+    //the weights are fixed now, allocations added while the vote is open don't count
+    let mut powers: Vec<(Addr, Uint128)> = Vec::new();
+    for (stage, users) in x.users.iter().enumerate() {
+        if is_gated_stage(&x.milestones, stage) {
+            continue;
+        }
+        for user in users.iter() {
+            match powers.iter_mut().find(|(wallet, _)| *wallet == user.wallet_address) {
+                Some((_, power)) => *power = power.checked_add(user.total_amount)?,
+                None => powers.push((user.wallet_address.clone(), user.total_amount)),
+            }
+        }
+    }
+    let mut total_power = Uint128::zero();
+    for (wallet, power) in powers.iter() {
+        total_power = total_power.checked_add(*power)?;
+        MILESTONE_POWER.save(deps.storage, (project_id.u128().into(), name.as_str(), wallet), power)?;
+    }
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    let milestone = x.milestones.iter_mut().find(|m| m.name == name)
+        .ok_or(ContractError::MilestoneNotFound{ name: name.clone() })?;
+    milestone.vote = Some(MilestoneVote{
+        end_time: now.checked_add(voting.voting_period)?,
+        yes: Uint128::zero(),
+        no: Uint128::zero(),
+        total_power,
+    });
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Start milestone vote")
+    .add_attribute("name", name))
+}
+
+pub fn try_votemilestone(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    name: String, approve: bool)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let power = MILESTONE_POWER
+        .may_load(deps.storage, (project_id.u128().into(), name.as_str(), &info.sender))?
+        .unwrap_or_default();
+    if power == Uint128::zero() {
+        return Err(ContractError::NoVotingPower{ });
+    }
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    let milestone = x.milestones.iter_mut().find(|m| m.name == name)
+        .ok_or(ContractError::MilestoneNotFound{ name: name.clone() })?;
+    let vote = match milestone.vote.as_mut() {
+        Some(vote) if now < vote.end_time => vote,
+        _ => return Err(ContractError::MilestoneVoteNotOpen{ name }),
+    };
+
+    let key = (project_id.u128().into(), name.as_str(), &info.sender);
+    if MILESTONE_VOTES.has(deps.storage, key.clone()) {
+        return Err(ContractError::AlreadyVoted{ name });
+    }
+    MILESTONE_VOTES.save(deps.storage, key, &approve)?;
+
+    if approve {
+        vote.yes = vote.yes.checked_add(power)?;
+    } else {
+        vote.no = vote.no.checked_add(power)?;
+    }
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Vote milestone")
+    .add_attribute("name", name)
+    .add_attribute("approve", approve.to_string())
+    .add_attribute("power", power))
+}
+
+pub fn try_tallymilestonevote(deps: DepsMut, _env: Env, project_id: Uint128, name: String)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let voting = x.milestone_voting.clone().ok_or(ContractError::MilestoneVotingDisabled{ })?;
+
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    let milestone = x.milestones.iter_mut().find(|m| m.name == name)
+        .ok_or(ContractError::MilestoneNotFound{ name: name.clone() })?;
+    if milestone.status != MilestoneStatus::Pending {
+        return Err(ContractError::MilestoneAlreadyDecided{ name });
+    }
+    let vote = milestone.vote.clone()
+        .ok_or(ContractError::MilestoneVoteNotOpen{ name: name.clone() })?;
+    if now < vote.end_time {
+        return Err(ContractError::MilestoneVoteOpen{ name });
+    }
+
+    let (quorum_reached, threshold_reached) = check_milestone_vote(&voting, &vote)?;
+    if quorum_reached && threshold_reached {
+        milestone.status = MilestoneStatus::Approved;
+        milestone.approved_at = now;
+    } else {
+        milestone.status = MilestoneStatus::Rejected;
+    }
+    let status = format!("{:?}", milestone.status);
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Tally milestone vote")
+    .add_attribute("name", name)
+    .add_attribute("status", status))
+}
+
+//stages released by milestones belong to the team, their holders don't vote
+pub fn is_gated_stage(milestones: &[Milestone], stage: usize) -> bool
+{
+    milestones.iter().any(|m| m.stage.u128() as usize == stage)
+}
+
+//returns whether the quorum and the approval threshold are reached
+pub fn check_milestone_vote(voting: &VotingConfig, vote: &MilestoneVote) -> Result<(bool, bool), ContractError>
+{
+    let cast = vote.yes.checked_add(vote.no)?;
+    let quorum_reached = cast.checked_mul(Uint128::new(100))? >= vote.total_power.checked_mul(voting.quorum)?
+        && cast > Uint128::zero();
+    let threshold_reached = vote.yes.checked_mul(Uint128::new(100))? >= cast.checked_mul(voting.threshold)?;
+    Ok((quorum_reached, threshold_reached))
+}
+
+pub fn calc_milestone_amount(milestone: &Milestone, total_amount: Uint128) -> Result<Uint128, ContractError>
+{
+    Ok(total_amount.checked_mul(milestone.percent)? / Uint128::new(100))
+}
+
+pub fn calc_milestone_unlocked(milestone: &Milestone, total_amount: Uint128, now: Uint128)
+    -> Result<Uint128, ContractError>
+{
+    if milestone.status != MilestoneStatus::Approved {
+        return Ok(Uint128::zero());
+    }
+
+    let amount = calc_milestone_amount(milestone, total_amount)?;
+    let past_time = now.checked_sub(milestone.approved_at)?;
+    if milestone.period == Uint128::zero() || past_time >= milestone.period {
+        return Ok(amount);
+    }
+    Ok(amount.checked_mul(past_time)? / milestone.period)
+}
+
+//part of an allocation following the time based schedule, shares gated by milestones of the stage are kept out
+pub fn calc_vesting_amount(milestones: &[Milestone], stage: usize, total_amount: Uint128) -> Result<Uint128, ContractError>
+{
+    let mut vesting_amount = total_amount;
+    for milestone in milestones.iter().filter(|m| m.stage.u128() as usize == stage) {
+        vesting_amount = vesting_amount.checked_sub(calc_milestone_amount(milestone, total_amount)?)?;
+    }
+    Ok(vesting_amount)
+}
+
+pub fn calc_time_unlocked(param: &VestingParameter, vesting_amount: Uint128, past_time: Uint128)
+    -> Result<Uint128, ContractError>
+{
+    if param.period == Uint128::zero() || param.soon > Uint128::new(100) {
+        return Err(ContractError::InvalidVestingParameter{ });
+    }
+
+    let mut unlocked = Uint128::zero();
+    if past_time > Uint128::zero() {
+        unlocked = vesting_amount.checked_mul(param.soon)? / Uint128::new(100);
+    }
+    let locked = vesting_amount.checked_sub(unlocked)?;
+    if past_time > param.after {
+        let vested = past_time.checked_sub(param.after)?.checked_mul(locked)? / param.period;
+        unlocked = unlocked.checked_add(vested)?;
+        if unlocked >= vesting_amount{
+            unlocked = vesting_amount;
+        }
+    }
+    Ok(unlocked)
+}
+
+//takes the project the caller already holds, loops over its users stay linear
+pub fn calc_pending(store: &dyn Storage, _env: Env, x: &ProjectInfo, user: UserInfo, stage: usize)
+    -> Result<Uint128, ContractError>
+{
+    let project_id = x.project_id;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if !is_release_started(&x.config, now) {
+        return Err(ContractError::ReleaseNotStarted{ });
+    }
+
+    let param = x.vest_param.get(stage).ok_or(ContractError::InvalidVestingParameter{ })?;
+    let past_time = now.checked_sub(x.config.start_time)?;
+    let unfunded = unfunded_purchase(store, project_id, stage, &user.wallet_address, now)?;
+    let funded_amount = user.total_amount.saturating_sub(unfunded);
+
+    let vesting_amount = calc_vesting_amount(&x.milestones, stage, funded_amount)?;
+    let unlocked = if user.early_claimed {
+        vesting_amount
+    } else {
+        calc_time_unlocked(param, vesting_amount, past_time)?
+    };
+
+    let mut unlocked_milestones = Uint128::zero();
+    for milestone in x.milestones.iter().filter(|m| m.stage.u128() as usize == stage) {
+        unlocked_milestones = unlocked_milestones
+            .checked_add(calc_milestone_unlocked(milestone, funded_amount, now)?)?;
+    }
+
+    let reward = calc_reward(&user, stage_reward_per_share(x, stage))?;
+
+    //a reduced or moved allocation can leave less unlocked than was already released
+    let unlocked = unlocked.checked_add(unlocked_milestones)?;
+    Ok(unlocked.saturating_sub(user.released_amount).checked_add(reward)?)
+}
+
+pub fn stage_reward_per_share(x: &ProjectInfo, stage: usize) -> Decimal
+{
+    x.reward_per_share.get(stage).copied().unwrap_or_else(Decimal::zero)
+}
+
+//participants that left the stage early don't share in forfeited tokens anymore
+pub fn calc_reward_weight(user: &UserInfo) -> Uint128
+{
+    if user.early_claimed {
+        return Uint128::zero();
+    }
+    user.total_amount
+}
+
+//share of the forfeited tokens of the stage that the user has not claimed yet
+pub fn calc_reward(user: &UserInfo, reward_per_share: Decimal) -> Result<Uint128, ContractError>
+{
+    let accrued = calc_reward_weight(user) * reward_per_share;
+    Ok(user.reward_amount.checked_add(accrued)?.checked_sub(user.reward_debt)?)
+}
+
+//allocation that is neither released nor unlocked yet
+pub fn calc_locked(store: &dyn Storage, _env: &Env, x: &ProjectInfo, user: &UserInfo, stage: usize)
+    -> Result<Uint128, ContractError>
+{
+    let remaining = user.total_amount.saturating_sub(user.released_amount);
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if !is_release_started(&x.config, now) {
+        return Ok(remaining);
+    }
+    let pending = calc_pending(store, _env.clone(), x, user.clone(), stage)?;
+    let reward = calc_reward(user, stage_reward_per_share(x, stage))?;
+    Ok(remaining.saturating_sub(pending.checked_sub(reward)?))
+}
+
+//keeps the accrued share before the reward weight of the user changes
+pub fn settle_reward(user: &mut UserInfo, reward_per_share: Decimal) -> Result<(), ContractError>
+{
+    user.reward_amount = calc_reward(user, reward_per_share)?;
+    reset_reward_debt(user, reward_per_share);
+    Ok(())
+}
+
+//starts accruing from the current accumulator, e.g. after the reward weight has changed
+pub fn reset_reward_debt(user: &mut UserInfo, reward_per_share: Decimal)
+{
+    user.reward_debt = calc_reward_weight(user) * reward_per_share;
+}
+
+//returns the unclaimed share and marks it as claimed
+pub fn claim_reward(user: &mut UserInfo, reward_per_share: Decimal) -> Result<Uint128, ContractError>
+{
+    let reward = calc_reward(user, reward_per_share)?;
+    user.reward_amount = Uint128::zero();
+    reset_reward_debt(user, reward_per_share);
+    Ok(reward)
+}
+
+//a failing TokenInfo query means the address is not a cw20 contract
+pub fn query_token_decimals(querier: &QuerierWrapper, token_addr: &str) -> Result<u8, ContractError>
+{
+    let token_info: TokenInfoResponse = querier.query_wasm_smart(
+        token_addr,
+        &Cw20QueryMsg::TokenInfo{}
+    ).map_err(|_| ContractError::InvalidToken{ token_addr: token_addr.to_string() })?;
+    Ok(token_info.decimals)
+}
+
+//base units of the cw20 token per project amount unit
+pub fn token_unit_factor(querier: &QuerierWrapper, config: &Config) -> Result<Uint128, ContractError>
+{
+    if config.units == AmountUnits::Raw {
+        return Ok(Uint128::new(1));
+    }
+    let decimals = match config.decimals {
+        Some(decimals) => decimals,
+        None => query_token_decimals(querier, &config.token_addr)?,
+    };
+    Ok(Uint128::new(10u128.pow(decimals as u32)))
+}
+
+//project amounts are kept in the project units, the cw20 token works in its base units
+pub fn to_token_amount(querier: &QuerierWrapper, config: &Config, amount: Uint128)
+    -> Result<Uint128, ContractError>
+{
+    Ok(amount.checked_mul(token_unit_factor(querier, config)?)?)
+}
+
+//projects share the contract balance, each one only pays out of what was deposited for it
+pub fn spend_escrow(store: &mut dyn Storage, project_id: Uint128, amount: Uint128)
+    -> Result<(), ContractError>
+{
+    let escrow = ESCROWS.may_load(store, project_id.u128().into())?.unwrap_or_default();
+    let escrow = escrow.checked_sub(amount).map_err(|_| ContractError::NotEnoughBalance{ })?;
+    ESCROWS.save(store, project_id.u128().into(), &escrow)?;
+    Ok(())
+}
+
+//the escrow is held in the project token, which can only be swapped while nothing is deposited
+pub fn check_token_change(store: &dyn Storage, x: &ProjectInfo, token_addr: &str)
+    -> Result<(), ContractError>
+{
+    let escrow = ESCROWS.may_load(store, x.project_id.u128().into())?.unwrap_or_default();
+    if token_addr != x.config.token_addr && !escrow.is_zero() {
+        return Err(ContractError::EscrowNotEmpty{ });
+    }
+    Ok(())
+}
+
+//what the project still owes over all stages, in token base units
+pub fn calc_obligation(querier: &QuerierWrapper, x: &ProjectInfo) -> Result<Uint128, ContractError>
+{
+    let mut obligation = Uint128::zero();
+    for stage in 0..x.users.len() {
+        obligation = obligation.checked_add(calc_stage_stats(x, stage)?.obligation)?;
+    }
+    to_token_amount(querier, &x.config, obligation)
+}
+
+//allocations that are not released yet have to be covered by the escrow of the project
+pub fn check_escrow(store: &dyn Storage, querier: &QuerierWrapper, x: &ProjectInfo)
+    -> Result<(), ContractError>
+{
+    let escrow = ESCROWS.may_load(store, x.project_id.u128().into())?.unwrap_or_default();
+    if calc_obligation(querier, x)? > escrow {
+        return Err(ContractError::NotEnoughBalance{ });
+    }
+    Ok(())
+}
+
+//tokens freed by removed or lowered allocations go back out, the obligation stays covered
+pub fn try_withdrawescrow(deps: DepsMut, info: MessageInfo, project_id: Uint128, amount: Uint128, recipient: Option<String>)
+    -> Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+    if amount.is_zero() {
+        return Err(ContractError::ZeroAmount{ });
+    }
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender,
+    };
+
+    let escrow = ESCROWS.may_load(deps.storage, project_id.u128().into())?.unwrap_or_default();
+    let surplus = escrow.saturating_sub(calc_obligation(&deps.querier, &x)?);
+    if amount > surplus {
+        return Err(ContractError::NotEnoughBalance{ });
+    }
+    spend_escrow(deps.storage, project_id, amount)?;
+
+    let msg = cw20_message(&x.config.token_addr, &Cw20ExecuteMsg::Transfer{
+        recipient: recipient.to_string(),
+        amount
+    })?;
+    Ok(Response::new()
+    .add_message(msg)
+    .add_attribute("action", "Withdraw escrow")
+    .add_attribute("recipient", recipient)
+    .add_attribute("amount", amount))
+}
+
+pub fn query_token_balance(querier: &QuerierWrapper, token_addr: &str, account: &Addr) -> Result<Uint128, ContractError>
+{
+    let res: Cw20BalanceResponse = querier.query_wasm_smart(
+        token_addr,
+        &Cw20QueryMsg::Balance{ address: account.to_string() }
+    ).map_err(|_| ContractError::InvalidToken{ token_addr: token_addr.to_string() })?;
+    Ok(res.balance)
+}
+
+//only the token of the project can fund it
+pub fn try_receive(deps: DepsMut, info: MessageInfo, wrapper: Cw20ReceiveMsg)
+    -> Result<Response, ContractError>
+{
+    match from_binary(&wrapper.msg)? {
+        ReceiveMsg::Deposit{ project_id } => {
+            let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+            if info.sender != x.config.token_addr {
+                return Err(ContractError::InvalidToken{ token_addr: info.sender.to_string() });
+            }
+            let escrow = ESCROWS.may_load(deps.storage, project_id.u128().into())?.unwrap_or_default();
+            ESCROWS.save(deps.storage, project_id.u128().into(), &escrow.checked_add(wrapper.amount)?)?;
+
+            Ok(Response::new()
+            .add_attribute("action", "Deposit")
+            .add_attribute("project_id", project_id)
+            .add_attribute("from", wrapper.sender)
+            .add_attribute("amount", wrapper.amount))
+        }
+    }
+}
+
+pub fn cw20_message(token_addr: &str, msg: &Cw20ExecuteMsg) -> StdResult<CosmosMsg>
+{
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token_addr.to_string(),
+        msg: to_binary(msg)?,
+        funds: Vec::new()
+    }))
+}
+
+pub fn try_earlyclaim(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_allowed(deps.storage, &deps.querier, &x, &info.sender)?;
+    check_not_frozen(deps.storage, project_id, &info.sender)?;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if !is_release_started(&x.config, now) {
+        return Err(ContractError::ReleaseNotStarted{ });
+    }
+    let past_time = now.checked_sub(x.config.start_time)?;
+
+    let mut amount = Uint128::zero();
+    let mut burn_amount = Uint128::zero();
+    let mut unshared = Uint128::zero();
+    let mut treasury_amounts: Vec<(String, Uint128)> = Vec::new();
+    for i in 0..x.users.len(){
+        let param = match x.vest_param.get(i) {
+            Some(param) => param.clone(),
+            None => continue,
+        };
+        let early_unlock = match &param.early_unlock {
+            Some(early_unlock) => early_unlock.clone(),
+            None => continue,
+        };
+        let index = match x.users[i].iter().position(|x| x.wallet_address == info.sender && x.token_id.is_none()) {
+            Some(index) if !x.users[i][index].early_claimed => index,
+            _ => continue,
+        };
+        if !unfunded_purchase(deps.storage, project_id, i, &info.sender, now)?.is_zero() {
+            continue;
+        }
+
+        let user = x.users[i][index].clone();
+        let reward_per_share = stage_reward_per_share(&x, i);
+        let pending_amount = calc_pending(deps.storage, _env.clone(), &x, user.clone(), i)?;
+        let vesting_amount = calc_vesting_amount(&x.milestones, i, user.total_amount)?;
+        let locked = vesting_amount.checked_sub(calc_time_unlocked(&param, vesting_amount, past_time)?)?;
+
+        //a decaying penalty shrinks linearly to zero at the end of the schedule
+        let mut penalty_percent = early_unlock.penalty;
+        if early_unlock.decay {
+            let duration = param.after.checked_add(param.period)?;
+            let remaining = duration - std::cmp::min(past_time, duration);
+            penalty_percent = penalty_percent.checked_mul(remaining)? / duration;
+        }
+        let penalty = std::cmp::min(locked.checked_mul(penalty_percent)? / Uint128::new(100), locked);
+
+        let reward = claim_reward(&mut x.users[i][index], reward_per_share)?;
+        x.users[i][index].early_claimed = true;
+        x.users[i][index].released_amount += pending_amount.checked_sub(reward)?.checked_add(locked)?;
+        reset_reward_debt(&mut x.users[i][index], reward_per_share);
+        amount += pending_amount.checked_add(locked)?.checked_sub(penalty)?;
+
+        match early_unlock.destination {
+            PenaltyDestination::Burn => burn_amount += penalty,
+            PenaltyDestination::Treasury{ address } => treasury_amounts.push((address, penalty)),
+            PenaltyDestination::Redistribute => {
+                if !redistribute_forfeited(&mut x, i, penalty, None) {
+                    unshared += penalty;
+                }
+            }
+        }
+    }
+
+    if amount == Uint128::zero() {
+        return Err(ContractError::NoPendingTokens{});
+    }
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    let token_addr = x.config.token_addr.as_str();
+    let amount = to_token_amount(&deps.querier, &x.config, amount)?;
+    let burn_amount = to_token_amount(&deps.querier, &x.config, burn_amount)?;
+    let mut messages = vec![cw20_message(token_addr, &Cw20ExecuteMsg::Transfer {
+        recipient: info.sender.to_string(),
+        amount,
+    })?];
+    let mut outgoing = amount;
+    if burn_amount > Uint128::zero() {
+        messages.push(cw20_message(token_addr, &Cw20ExecuteMsg::Burn { amount: burn_amount })?);
+        outgoing += burn_amount;
+    }
+    for (address, penalty) in treasury_amounts {
+        let penalty = to_token_amount(&deps.querier, &x.config, penalty)?;
+        if penalty == Uint128::zero() {
+            continue;
+        }
+        messages.push(cw20_message(token_addr, &Cw20ExecuteMsg::Transfer {
+            recipient: deps.api.addr_validate(&address)?.to_string(),
+            amount: penalty,
+        })?);
+        outgoing += penalty;
+    }
+    spend_escrow(deps.storage, project_id, outgoing)?;
+
+    Ok(Response::new()
+    .add_messages(messages)
+    .add_attribute("action", "Early claim")
+    .add_attribute("amount", amount)
+    .add_attribute("unshared", unshared))
+}
+
+//shares forfeited tokens between the remaining stage participants, pro rata to their allocations.
+//only the accumulator of the stage moves, every user picks up the share on the next claim.
+//the entry at `except` gives the tokens up and takes no part, its debt has to be reset afterwards.
+//false when nobody is left to share, the tokens then stay in the escrow above the obligation
+pub fn redistribute_forfeited(x: &mut ProjectInfo, stage: usize, amount: Uint128, except: Option<usize>) -> bool
+{
+    if amount == Uint128::zero() {
+        return true;
+    }
+    let total_weight = x.users[stage].iter().enumerate()
+        .filter(|(index, _)| Some(*index) != except)
+        .fold(Uint128::zero(), |sum, (_, user)| sum + calc_reward_weight(user));
+    if total_weight == Uint128::zero() {
+        return false;
+    }
+
+    if x.reward_per_share.len() <= stage {
+        x.reward_per_share.resize(stage + 1, Decimal::zero());
+    }
+    x.reward_per_share[stage] = x.reward_per_share[stage] + Decimal::from_ratio(amount, total_weight);
+    true
+}
+
+//allocation the admin takes back is shared out when the stage is set up for it
+pub fn redistribute_revoked(x: &mut ProjectInfo, stage: usize, amount: Uint128, except: Option<usize>) -> bool
+{
+    match x.vest_param.get(stage) {
+        Some(param) if param.redistribute_revoked => redistribute_forfeited(x, stage, amount, except),
+        _ => false,
+    }
+}
+
+//marks what the user at `index` of the stage can claim as released and returns the amount
+pub fn release_user_pending(store: &dyn Storage, _env: &Env, x: &mut ProjectInfo,
+    stage: usize, index: usize)
+    -> Result<Uint128, ContractError>
+{
+    let pending_amount = calc_pending(
+        store, _env.clone(), x, x.users[stage][index].clone(), stage
+    )?;
+    let reward_per_share = stage_reward_per_share(x, stage);
+    let reward = claim_reward(&mut x.users[stage][index], reward_per_share)?;
+    x.users[stage][index].released_amount += pending_amount.checked_sub(reward)?;
+    Ok(pending_amount)
+}
+
+//marks everything the wallet can claim in every stage as released and returns the amount,
+//tokenized allocations are left to the owner of their position
+pub fn release_pending(store: &dyn Storage, _env: &Env, x: &mut ProjectInfo, wallet: &Addr)
+    -> Result<Uint128, ContractError>
+{
+    let mut amount = Uint128::zero();
+    for i in 0..x.users.len(){
+        let index = x.users[i].iter().position(|x| &x.wallet_address == wallet && x.token_id.is_none());
+        if let Some(index) = index {
+            amount = amount.checked_add(release_user_pending(store, _env, x, i, index)?)?;
+        }
+    }
+    Ok(amount)
+}
+
+//pays out the next `limit` users of the stage after the stored cursor, starting over once the
+//end of the stage is reached
+pub fn try_distribute(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, stage: Uint128,
+    limit: u32)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let key = (U128Key::from(project_id.u128()), U128Key::from(stage.u128()));
+    let users = x.users.get(stage.u128() as usize).ok_or(ContractError::InvalidStage{ stage })?;
+    let stage = stage.u128() as usize;
+    //continue after the last wallet paid, from the start when it was removed meanwhile
+    let cursor = match DISTRIBUTE_CURSORS.may_load(deps.storage, key.clone())? {
+        Some(last) => users.iter().rposition(|user| user.wallet_address == last).map_or(0, |index| index + 1),
+        None => 0,
+    };
+    let end = std::cmp::min(cursor + limit as usize, users.len());
+
+    let mut payouts: Vec<(Addr, Uint128)> = Vec::new();
+    for index in cursor..end {
+        //rejected and frozen wallets keep their tokens until they can claim themselves
+        let wallet = &x.users[stage][index].wallet_address;
+        if !is_allowed(deps.storage, &deps.querier, &x, wallet)?
+            || FROZEN_USERS.has(deps.storage, (project_id.u128().into(), wallet)) {
+            continue;
+        }
+        let amount = release_user_pending(deps.storage, &_env, &mut x, stage, index)?;
+        if amount > Uint128::zero() {
+            let recipient = match &x.users[stage][index].token_id {
+                Some(token_id) => POSITIONS.load(deps.storage, token_id)?.owner,
+                None => x.users[stage][index].wallet_address.clone(),
+            };
+            payouts.push((recipient, amount));
+        }
+    }
+
+    let done = end >= x.users[stage].len();
+    let next_cursor = if done || end == 0 {
+        DISTRIBUTE_CURSORS.remove(deps.storage, key);
+        String::new()
+    } else {
+        let last = x.users[stage][end - 1].wallet_address.clone();
+        DISTRIBUTE_CURSORS.save(deps.storage, key, &last)?;
+        last.to_string()
+    };
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    let token_addr = x.config.token_addr.as_str();
+    let factor = token_unit_factor(&deps.querier, &x.config)?;
+    let mut messages = Vec::new();
+    let mut total = Uint128::zero();
+    for (wallet, amount) in payouts.iter() {
+        let amount = amount.checked_mul(factor)?;
+        messages.push(cw20_message(token_addr, &Cw20ExecuteMsg::Transfer {
+            recipient: wallet.to_string(),
+            amount,
+        })?);
+        total += amount;
+    }
+    spend_escrow(deps.storage, project_id, total)?;
+
+    Ok(Response::new()
+    .add_messages(messages)
+    .add_attribute("action", "Distribute")
+    .add_attribute("paid_users", payouts.len().to_string())
+    .add_attribute("amount", total)
+    .add_attribute("cursor", next_cursor)
+    .add_attribute("done", done.to_string()))
+}
+
+//resolves whose tokens are claimed and where they go, the sender needs a grant to claim for others
+pub fn check_claimer(deps: Deps, project_id: Uint128, sender: &Addr,
+    beneficiary: Option<String>, recipient: Option<String>)
+    -> Result<(Addr, Addr), ContractError>
+{
+    let wallet = match beneficiary {
+        Some(beneficiary) => deps.api.addr_validate(&beneficiary)?,
+        None => sender.clone(),
+    };
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => wallet.clone(),
+    };
+
+    if &wallet != sender {
+        let grant = CLAIM_GRANTS.may_load(deps.storage, (project_id.u128().into(), &wallet, sender))?
+            .ok_or(ContractError::Unauthorized{ })?;
+        if recipient != wallet && !grant.allow_recipient {
+            return Err(ContractError::RecipientNotAllowed{ });
+        }
+    }
+    Ok((wallet, recipient))
+}
+
+pub fn try_claimandsend(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    contract: String, msg: Binary, beneficiary: Option<String>)
+    ->Result<Response, ContractError>
+{
+    let (wallet, contract) = check_claimer(deps.as_ref(), project_id, &info.sender, beneficiary, Some(contract))?;
+
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_allowed(deps.storage, &deps.querier, &x, &wallet)?;
+    check_not_frozen(deps.storage, project_id, &wallet)?;
+    if let Some(targets) = &x.config.send_targets {
+        if !targets.contains(&contract) {
+            return Err(ContractError::SendTargetNotAllowed{ contract: contract.to_string() });
+        }
+    }
+
+    let amount = release_pending(deps.storage, &_env, &mut x, &wallet)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::NoPendingTokens{});
+    }
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    let token_addr = x.config.token_addr.as_str();
+    let amount = to_token_amount(&deps.querier, &x.config, amount)?;
+    spend_escrow(deps.storage, project_id, amount)?;
+
+    let send_cw20 = cw20_message(token_addr, &Cw20ExecuteMsg::Send {
+        contract: contract.to_string(),
+        amount,
+        msg,
+    })?;
+
+    Ok(Response::new()
+    .add_message(send_cw20)
+    .add_attribute("action", "Claim and send")
+    .add_attribute("wallet", wallet)
+    .add_attribute("contract", contract))
+}
+
+pub fn try_setsendtargets(deps: DepsMut, info: MessageInfo, project_id: Uint128, targets: Option<Vec<String>>)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    x.config.send_targets = match targets {
+        Some(targets) => Some(targets.iter()
+            .map(|target| deps.api.addr_validate(target))
+            .collect::<StdResult<Vec<Addr>>>()?),
+        None => None,
+    };
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    Ok(Response::new()
+    .add_attribute("action", "Set send targets"))
+}
+
+pub fn try_grantclaim(deps: DepsMut, info: MessageInfo, project_id: Uint128, grantee: String,
+    allow_recipient: bool)
+    ->Result<Response, ContractError>
+{
+    //make sure the project exists
+    PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+
+    let grantee = deps.api.addr_validate(&grantee)?;
+    CLAIM_GRANTS.save(deps.storage, (project_id.u128().into(), &info.sender, &grantee), &ClaimGrant{
+        grantee: grantee.clone(),
+        allow_recipient,
+    })?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Grant claim")
+    .add_attribute("grantee", grantee))
+}
+
+pub fn try_revokeclaimgrant(deps: DepsMut, info: MessageInfo, project_id: Uint128, grantee: String)
+    ->Result<Response, ContractError>
+{
+    let grantee = deps.api.addr_validate(&grantee)?;
+    CLAIM_GRANTS.remove(deps.storage, (project_id.u128().into(), &info.sender, &grantee));
+
+    Ok(Response::new()
+    .add_attribute("action", "Revoke claim grant")
+    .add_attribute("grantee", grantee))
+}
+
+pub fn try_claimpendingtokens(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    beneficiary: Option<String>, recipient: Option<String> )
+    ->Result<Response, ContractError>
+{
+    let (wallet, recipient) = check_claimer(deps.as_ref(), project_id, &info.sender, beneficiary, recipient)?;
+
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_allowed(deps.storage, &deps.querier, &x, &wallet)?;
+    check_not_frozen(deps.storage, project_id, &wallet)?;
+    let amount = release_pending(deps.storage, &_env, &mut x, &wallet)?;
+
+    if amount == Uint128::zero() {
+        return Err(ContractError::NoPendingTokens{});
+    }
+
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    let token_addr = x.config.token_addr.as_str();
+    let amount = to_token_amount(&deps.querier, &x.config, amount)?;
+    spend_escrow(deps.storage, project_id, amount)?;
+
+    let bank_cw20 = cw20_message(token_addr, &Cw20ExecuteMsg::Transfer {
+        recipient: recipient.to_string(),
+        amount,
+    })?;
+
+    Ok(Response::new()
+    .add_message(bank_cw20)
+    .add_attribute("action", "Claim pending tokens")
+    .add_attribute("wallet", wallet)
+    .add_attribute("recipient", recipient))
+}
+
+pub fn check_add_userinfo( users: &mut Vec<UserInfo>, wallet:Addr, amount: Uint128, reward_per_share: Decimal)
+    -> Result<(), ContractError>
+{
+    //a tokenized allocation may belong to someone else by now, it never grows
+    let index =users.iter().position(|x| x.wallet_address == wallet && x.token_id.is_none());
+    if let Some(index) = index {
+        settle_reward(&mut users[index], reward_per_share)?;
+        users[index].total_amount += amount;
+        reset_reward_debt(&mut users[index], reward_per_share);
+    }
+    else{
+        let mut user = UserInfo { 
+            wallet_address: wallet, 
+            total_amount: amount, 
+            released_amount: Uint128::zero(), 
+            pending_amount: Uint128::zero(),
+            early_claimed: false,
+            reward_debt: Uint128::zero(),
+            reward_amount: Uint128::zero(),
+            frozen: false,
+            token_id: None,
+        };
+        reset_reward_debt(&mut user, reward_per_share);
+        users.push(user);
+    }
+    Ok(())
+}
+//the allocation the wallet still holds itself, a tokenized one belongs to the NFT owner
+pub fn find_user(users: &[UserInfo], wallet: &Addr) -> Result<usize, ContractError>
+{
+    if let Some(index) = users.iter().position(|user| &user.wallet_address == wallet && user.token_id.is_none()) {
+        return Ok(index);
+    }
+    match users.iter().find_map(|user| match &user.token_id {
+        Some(token_id) if &user.wallet_address == wallet => Some(token_id.clone()),
+        _ => None,
+    }) {
+        Some(token_id) => Err(ContractError::PositionTokenized{ token_id }),
+        None => Err(ContractError::UserNotFound{ wallet: wallet.to_string() }),
+    }
+}
+
+pub fn try_adduser(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:Uint128, wallet:Addr, amount: Uint128)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    check_allowed(deps.storage, &deps.querier, &x, &wallet)?;
+
+    if stage.u128() as usize >= x.users.len() {
+        return Err(ContractError::InvalidStage{ stage });
+    }
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    check_add_userinfo(&mut x.users[stage], wallet, amount, reward_per_share)?;
+    check_escrow(deps.storage, &deps.querier, &x)?;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "Add  User info"))
+}
+
+pub fn try_setsale(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage: Uint128, sale: Option<SaleConfig>)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+    if stage.u128() as usize >= x.users.len() {
+        return Err(ContractError::InvalidStage{ stage });
+    }
+
+    let key = (project_id.u128().into(), stage.u128().into());
+    match sale {
+        Some(config) => {
+            if config.price == Uint128::zero() || config.start_time >= config.end_time
+                || config.min_amount > config.max_amount {
+                return Err(ContractError::InvalidSale{ });
+            }
+            for wallet in config.whitelist.iter().flatten() {
+                deps.api.addr_validate(wallet.as_str())?;
+            }
+            let current = SALES.may_load(deps.storage, key.clone())?;
+            check_sale_change(current.as_ref(), Some(&config))?;
+            //sold and raised survive a config change
+            let sale = match current {
+                Some(sale) => Sale{ config, ..sale },
+                None => Sale{ config, sold: Uint128::zero(), raised: Uint128::zero(), withdrawn: false },
+            };
+            SALES.save(deps.storage, key, &sale)?;
+        }
+        None => {
+            check_sale_change(SALES.may_load(deps.storage, key.clone())?.as_ref(), None)?;
+            SALES.remove(deps.storage, key.clone());
+            //a later sale on the stage starts without the buyers of this one
+            let buyers: Vec<Vec<u8>> = PURCHASES.prefix(key.clone())
+                .keys(deps.storage, None, None, Order::Ascending)
+                .collect();
+            for buyer in buyers.iter() {
+                let buyer = Addr::unchecked(String::from_utf8_lossy(buyer));
+                PURCHASES.remove(deps.storage, (key.0.clone(), key.1.clone(), &buyer));
+                CONTRIBUTIONS.remove(deps.storage, (key.0.clone(), key.1.clone(), &buyer));
+            }
+        }
+    }
+
+    Ok(Response::new()
+    .add_attribute("action", "Set sale"))
+}
+
+//contributors of a soft-capped sale rely on its terms until it resolves,
+//and funds already paid out to the admin can't become refundable afterwards
+pub fn check_sale_change(sale: Option<&Sale>, config: Option<&SaleConfig>) -> Result<(), ContractError>
+{
+    let sale = match sale {
+        Some(sale) if sale.raised > Uint128::zero() => sale,
+        _ => return Ok(()),
+    };
+    if sale.config.soft_cap.is_some() {
+        return Err(ContractError::InvalidSale{ });
+    }
+    match config {
+        Some(config) if config.soft_cap.is_some() => Err(ContractError::InvalidSale{ }),
+        _ => Ok(()),
+    }
+}
+
+pub fn sale_status(sale: &Sale, now: Uint128) -> SaleStatus
+{
+    if now < sale.config.end_time {
+        return SaleStatus::Open;
+    }
+    match sale.config.soft_cap {
+        Some(soft_cap) if sale.raised < soft_cap => SaleStatus::Failed,
+        _ => SaleStatus::Succeeded,
+    }
+}
+
+//allocation the wallet bought in a soft-capped sale, it only vests once the sale succeeded
+pub fn unfunded_purchase(store: &dyn Storage, project_id: Uint128, stage: usize, wallet: &Addr, now: Uint128)
+    -> StdResult<Uint128>
+{
+    let sale = SALES.may_load(store, (project_id.u128().into(), (stage as u128).into()))?;
+    match sale {
+        Some(sale) if sale.config.soft_cap.is_some() && sale_status(&sale, now) != SaleStatus::Succeeded
+            => Ok(PURCHASES.may_load(store, (project_id.u128().into(), (stage as u128).into(), wallet))?.unwrap_or_default()),
+        _ => Ok(Uint128::zero()),
+    }
+}
+
+//buys as much allocation as the sent funds pay for, the rest goes back to the buyer
+pub fn try_purchase(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, stage: Uint128)
+    ->Result<Response, ContractError>
+{
+    let key = (project_id.u128().into(), stage.u128().into());
+    let mut sale = SALES.may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::SaleNotOpen{ stage })?;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if now < sale.config.start_time || now >= sale.config.end_time {
+        return Err(ContractError::SaleNotOpen{ stage });
+    }
+    if let Some(whitelist) = &sale.config.whitelist {
+        if !whitelist.contains(&info.sender) {
+            return Err(ContractError::NotWhitelisted{ });
+        }
+    }
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_allowed(deps.storage, &deps.querier, &x, &info.sender)?;
+
+    //only the sale denom is refunded, anything else would stay in the contract
+    if let Some(coin) = info.funds.iter().find(|coin| coin.denom != sale.config.denom) {
+        return Err(ContractError::UnexpectedFunds{ denom: coin.denom.clone() });
+    }
+    let paid = info.funds.iter().fold(Uint128::zero(), |sum, coin| sum + coin.amount);
+    let amount = paid / sale.config.price;
+    let cost = amount.checked_mul(sale.config.price)?;
+
+    let purchase_key = (project_id.u128().into(), stage.u128().into(), &info.sender);
+    let purchased = PURCHASES.may_load(deps.storage, purchase_key.clone())?
+        .unwrap_or_default().checked_add(amount)?;
+    if amount == Uint128::zero() || purchased < sale.config.min_amount || purchased > sale.config.max_amount {
+        return Err(ContractError::PurchaseOutOfRange{ });
+    }
+    PURCHASES.save(deps.storage, purchase_key.clone(), &purchased)?;
+
+    //only a soft-capped sale holds funds that can be refunded
+    if sale.config.soft_cap.is_some() {
+        let contributed = CONTRIBUTIONS.may_load(deps.storage, purchase_key.clone())?.unwrap_or_default();
+        CONTRIBUTIONS.save(deps.storage, purchase_key, &contributed.checked_add(cost)?)?;
+    }
+
+    sale.sold = sale.sold.checked_add(amount)?;
+    sale.raised = sale.raised.checked_add(cost)?;
+    SALES.save(deps.storage, key, &sale)?;
+
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    check_add_userinfo(&mut x.users[stage], info.sender.clone(), amount, reward_per_share)?;
+    check_escrow(deps.storage, &deps.querier, &x)?;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    //a soft-capped sale keeps the funds until it resolves
+    let mut messages = Vec::new();
+    if sale.config.soft_cap.is_none() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: x.config.owner.to_string(),
+            amount: vec![Coin::new(cost.u128(), &sale.config.denom)],
+        }));
+    }
+    let change = paid.checked_sub(cost)?;
+    if change > Uint128::zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![Coin::new(change.u128(), &sale.config.denom)],
+        }));
+    }
+
+    Ok(Response::new()
+    .add_messages(messages)
+    .add_attribute("action", "Purchase")
+    .add_attribute("amount", amount))
+}
+
+//gives a contributor of a failed sale the funds back and takes back the bought allocation
+pub fn try_refund(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, stage: Uint128)
+    ->Result<Response, ContractError>
+{
+    let key = (project_id.u128().into(), stage.u128().into());
+    let sale = SALES.may_load(deps.storage, key)?.ok_or(ContractError::SaleNotFailed{ stage })?;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if sale_status(&sale, now) != SaleStatus::Failed {
+        return Err(ContractError::SaleNotFailed{ stage });
+    }
+
+    let purchase_key = (project_id.u128().into(), stage.u128().into(), &info.sender);
+    let contributed = CONTRIBUTIONS.may_load(deps.storage, purchase_key.clone())?.unwrap_or_default();
+    if contributed == Uint128::zero() {
+        return Err(ContractError::NothingToWithdraw{ });
+    }
+    let purchased = PURCHASES.may_load(deps.storage, purchase_key.clone())?.unwrap_or_default();
+    CONTRIBUTIONS.remove(deps.storage, purchase_key.clone());
+    PURCHASES.remove(deps.storage, purchase_key);
+
+    //nothing of a failed stage was released, the bought part simply goes away
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    if let Some(index) = x.users[stage].iter().position(|x| x.wallet_address == info.sender && x.token_id.is_none()) {
+        let user = &mut x.users[stage][index];
+        settle_reward(user, reward_per_share)?;
+        user.total_amount = user.total_amount.saturating_sub(purchased);
+        reset_reward_debt(user, reward_per_share);
+        if user.total_amount == Uint128::zero() && user.released_amount == Uint128::zero() {
+            x.users[stage].remove(index);
+        }
+    }
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    Ok(Response::new()
+    .add_message(CosmosMsg::Bank(BankMsg::Send {
+        to_address: info.sender.to_string(),
+        amount: vec![Coin::new(contributed.u128(), &sale.config.denom)],
+    }))
+    .add_attribute("action", "Refund")
+    .add_attribute("amount", contributed))
+}
+
+pub fn try_withdrawraised(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, stage: Uint128)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let key = (project_id.u128().into(), stage.u128().into());
+    let mut sale = SALES.may_load(deps.storage, key.clone())?.ok_or(ContractError::SaleNotSucceeded{ stage })?;
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    if sale_status(&sale, now) != SaleStatus::Succeeded {
+        return Err(ContractError::SaleNotSucceeded{ stage });
+    }
+    //sales without a soft cap already paid the admin on every purchase
+    if sale.config.soft_cap.is_none() || sale.withdrawn || sale.raised == Uint128::zero() {
+        return Err(ContractError::NothingToWithdraw{ });
+    }
+    sale.withdrawn = true;
+    SALES.save(deps.storage, key, &sale)?;
+
+    Ok(Response::new()
+    .add_message(CosmosMsg::Bank(BankMsg::Send {
+        to_address: x.config.owner.to_string(),
+        amount: vec![Coin::new(sale.raised.u128(), &sale.config.denom)],
+    }))
+    .add_attribute("action", "Withdraw raised")
+    .add_attribute("amount", sale.raised))
+}
+
+pub fn is_allowed(store: &dyn Storage, querier: &QuerierWrapper, x: &ProjectInfo, wallet: &Addr)
+    -> StdResult<bool>
+{
+    let allowlist = match &x.config.allowlist {
+        Some(allowlist) => allowlist,
+        None => return Ok(true),
+    };
+    if ALLOWLIST.has(store, (x.project_id.u128().into(), wallet)) {
+        return Ok(true);
+    }
+    match &allowlist.registry {
+        Some(registry) => {
+            let res: IsAllowedResponse = querier.query_wasm_smart(
+                registry,
+                &RegistryQueryMsg::IsAllowed{ address: wallet.to_string() }
+            )?;
+            Ok(res.allowed)
+        }
+        None => Ok(false),
+    }
+}
+
+pub fn check_allowed(store: &dyn Storage, querier: &QuerierWrapper, x: &ProjectInfo, wallet: &Addr)
+    -> Result<(), ContractError>
+{
+    if !is_allowed(store, querier, x, wallet)? {
+        return Err(ContractError::WalletNotAllowed{ wallet: wallet.to_string() });
+    }
+    Ok(())
+}
+
+pub fn check_not_frozen(store: &dyn Storage, project_id: Uint128, wallet: &Addr) -> Result<(), ContractError>
+{
+    if FROZEN_USERS.has(store, (project_id.u128().into(), wallet)) {
+        return Err(ContractError::UserFrozen{ wallet: wallet.to_string() });
+    }
+    Ok(())
+}
+
+//a frozen user keeps vesting and gets everything unlocked meanwhile on the first claim after unfreezing
+pub fn try_setfrozen(deps: DepsMut, info: MessageInfo, project_id: Uint128, wallet: String, frozen: bool)
+    ->Result<Response, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    let wallet = deps.api.addr_validate(&wallet)?;
+    if !x.users.iter().any(|users| users.iter().any(|user| user.wallet_address == wallet)) {
+        return Err(ContractError::UserNotFound{ wallet: wallet.to_string() });
+    }
+    if frozen {
+        FROZEN_USERS.save(deps.storage, (project_id.u128().into(), &wallet), &true)?;
+    } else {
+        FROZEN_USERS.remove(deps.storage, (project_id.u128().into(), &wallet));
+    }
+
+    Ok(Response::new()
+    .add_attribute("action", if frozen { "Freeze user" } else { "Unfreeze user" })
+    .add_attribute("wallet", wallet))
 }
 
-pub fn try_startrelease(deps: DepsMut, info:MessageInfo, project_id: Uint128, start_time: Uint128)
+pub fn try_setallowlist(deps: DepsMut, info: MessageInfo, project_id: Uint128, allowlist: Option<AllowlistConfig>)
     ->Result<Response, ContractError>
 {
     let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
-    let owner = OWNER.load(deps.storage).unwrap();
+    let owner = OWNER.load(deps.storage)?;
     if info.sender != owner && info.sender != x.config.owner {
         return Err(ContractError::Unauthorized{ });
     }
 
-    x.config.start_time = start_time;
+    if let Some(registry) = allowlist.as_ref().and_then(|allowlist| allowlist.registry.as_ref()) {
+        deps.api.addr_validate(registry)?;
+    }
+    x.config.allowlist = allowlist;
     PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
     Ok(Response::new()
-    .add_attribute("action", "Start Release"))  
+    .add_attribute("action", "Set allowlist"))
 }
 
-pub fn try_setprojectinfo(deps: DepsMut, info: MessageInfo, project_id: Uint128, project_info: ProjectInfo)
+pub fn try_updateallowlist(deps: DepsMut, info: MessageInfo, project_id: Uint128, add: Vec<String>, remove: Vec<String>)
     ->Result<Response, ContractError>
 {
-    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
-    let owner = OWNER.load(deps.storage).unwrap();
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
     if info.sender != owner && info.sender != x.config.owner {
         return Err(ContractError::Unauthorized{ });
     }
 
-    x = project_info;
-    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    for wallet in add {
+        let wallet = deps.api.addr_validate(&wallet)?;
+        ALLOWLIST.save(deps.storage, (project_id.u128().into(), &wallet), &true)?;
+    }
+    for wallet in remove {
+        let wallet = deps.api.addr_validate(&wallet)?;
+        ALLOWLIST.remove(deps.storage, (project_id.u128().into(), &wallet));
+    }
+
     Ok(Response::new()
-    .add_attribute("action", "set Project Info"))    
+    .add_attribute("action", "Update allowlist"))
 }
-pub fn try_setvestingparameters(deps: DepsMut, info: MessageInfo, project_id: Uint128, params: Vec<VestingParameter>)
+
+//merges a batch of allocations into the stage, released amounts are never touched
+pub fn try_addusers(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:Uint128, users: Vec<UserAllocation>)
     ->Result<Response, ContractError>
 {
     let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
@@ -110,108 +2293,193 @@ pub fn try_setvestingparameters(deps: DepsMut, info: MessageInfo, project_id: Ui
         return Err(ContractError::Unauthorized{ });
     }
 
-    x.vest_param = params;
+    let mut wallets: Vec<Addr> = Vec::new();
+    for user in users.iter() {
+        let wallet = deps.api.addr_validate(&user.wallet)?;
+        if wallets.contains(&wallet) {
+            return Err(ContractError::DuplicateWallet{ wallet: wallet.to_string() });
+        }
+        check_allowed(deps.storage, &deps.querier, &x, &wallet)?;
+        wallets.push(wallet);
+    }
+
+    if stage.u128() as usize >= x.users.len() {
+        return Err(ContractError::InvalidStage{ stage });
+    }
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    let mut added = 0u64;
+    let mut updated = 0u64;
+    for (wallet, user) in wallets.into_iter().zip(users.iter()) {
+        if x.users[stage].iter().any(|x| x.wallet_address == wallet && x.token_id.is_none()) {
+            updated += 1;
+        } else {
+            added += 1;
+        }
+        check_add_userinfo(&mut x.users[stage], wallet, user.amount, reward_per_share)?;
+    }
 
+    check_escrow(deps.storage, &deps.querier, &x)?;
     PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
     Ok(Response::new()
-    .add_attribute("action", "Set Vesting parameters"))
+    .add_attribute("action", "Add users")
+    .add_attribute("added", added.to_string())
+    .add_attribute("updated", updated.to_string()))
 }
 
-pub fn calc_pending(store: &dyn Storage, _env: Env, project_id: Uint128, user: UserInfo, stage: usize)
-    -> Uint128
+pub fn try_setallocation(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:Uint128,
+    wallet: String, amount: Uint128)
+    ->Result<Response, ContractError>
 {
-    let x = PROJECT_INFOS.load(store, project_id.u128().into()).unwrap();
-    if x.config.start_time == Uint128::zero() {
-        return Uint128::zero();
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
     }
 
-    let param = x.vest_param[stage];
+    let wallet = deps.api.addr_validate(&wallet)?;
+    let users = x.users.get(stage.u128() as usize).ok_or(ContractError::InvalidStage{ stage })?;
+    let index = find_user(users, &wallet)?;
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
 
-    let past_time = Uint128::new(_env.block.time.seconds() as u128) - x.config.start_time;
-
-    let mut unlocked = Uint128::zero();
-    if past_time > Uint128::zero() {
-        unlocked = user.total_amount * param.soon / Uint128::new(100);
+    let user = &mut x.users[stage][index];
+    if amount < user.released_amount {
+        return Err(ContractError::AllocationBelowReleased{ released: user.released_amount });
     }
-    let locked = user.total_amount - unlocked;
-    if past_time > param.after {
-        unlocked += (past_time - param.after) * locked / param.period;
-        if unlocked >= user.total_amount{
-            unlocked = user.total_amount;
-        }
+    let increased = amount > user.total_amount;
+    let revoked = user.total_amount.saturating_sub(amount);
+    settle_reward(user, reward_per_share)?;
+    user.total_amount = amount;
+
+    //the user doesn't share in the part it just lost
+    redistribute_revoked(&mut x, stage, revoked, Some(index));
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    reset_reward_debt(&mut x.users[stage][index], reward_per_share);
+
+    let stats = calc_stage_stats(&x, stage)?;
+    if increased {
+        check_escrow(deps.storage, &deps.querier, &x)?;
     }
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
 
-    return unlocked - user.released_amount;
+    Ok(Response::new()
+    .add_attribute("action", "Set allocation")
+    .add_attribute("wallet", wallet)
+    .add_attribute("stage_total", stats.total_amount)
+    .add_attribute("obligation", stats.obligation))
 }
 
-pub fn try_claimpendingtokens(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128 )
+pub fn try_removeuser(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:Uint128, wallet: String)
     ->Result<Response, ContractError>
 {
     let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
-    let mut amount = Uint128::zero();
-    for i in 0..x.users.len()-1{
-        let index = x.users[i].iter().position(|x| x.wallet_address == info.sender);
-        if index != None {
-            let pending_amount = calc_pending(
-                deps.storage, _env.clone(), project_id, x.users[i][index.unwrap()].clone(), i
-            );
-            x.users[i][index.unwrap()].released_amount += pending_amount;
-            amount += pending_amount;
-        }
+    let owner = OWNER.load(deps.storage).unwrap();
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
     }
 
-    if amount == Uint128::zero() {
-        return Err(ContractError::NoPendingTokens{});
+    let wallet = deps.api.addr_validate(&wallet)?;
+    let users = x.users.get(stage.u128() as usize).ok_or(ContractError::InvalidStage{ stage })?;
+    let index = find_user(users, &wallet)?;
+    let stage = stage.u128() as usize;
+
+    //the unclaimed share of forfeited tokens goes back to the remaining participants,
+    //a user that already claimed keeps the released part as its allocation
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    let kept = x.users[stage][index].released_amount > Uint128::zero();
+    let (reward, revoked) = if kept {
+        let user = &mut x.users[stage][index];
+        let reward = claim_reward(user, reward_per_share)?;
+        let revoked = user.total_amount.saturating_sub(user.released_amount);
+        user.total_amount = user.released_amount;
+        (reward, revoked)
+    } else {
+        let user = x.users[stage].remove(index);
+        (calc_reward(&user, reward_per_share)?, user.total_amount)
+    };
+    let except = if kept { Some(index) } else { None };
+    redistribute_forfeited(&mut x, stage, reward, except);
+    redistribute_revoked(&mut x, stage, revoked, except);
+    if kept {
+        let reward_per_share = stage_reward_per_share(&x, stage);
+        reset_reward_debt(&mut x.users[stage][index], reward_per_share);
     }
 
+    let stats = calc_stage_stats(&x, stage)?;
     PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
 
-    let token_info: TokenInfoResponse = deps.querier.query_wasm_smart(
-        x.config.token_addr.clone(),
-        &Cw20QueryMsg::TokenInfo{}
-    )?;
-    amount = amount * Uint128::new((10 as u128).pow(token_info.decimals as u32)); //for decimals
+    Ok(Response::new()
+    .add_attribute("action", "Remove user")
+    .add_attribute("wallet", wallet)
+    .add_attribute("stage_total", stats.total_amount)
+    .add_attribute("obligation", stats.obligation))
+}
 
-    let token_balance: Cw20BalanceResponse = deps.querier.query_wasm_smart(
-        x.config.token_addr.clone(),
-        &Cw20QueryMsg::Balance{
-            address: _env.contract.address.to_string(),
-        }
-    )?;
-    if token_balance.balance < amount {
-        return Err(ContractError::NotEnoughBalance{})
+//takes back everything of an allocation that was not released yet, vested or not,
+//accrued shares of forfeited tokens stay claimable
+pub fn try_clawback(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, stage: Uint128,
+    wallet: String, recipient: Option<String>)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
     }
 
-    let bank_cw20 = WasmMsg::Execute {
-        contract_addr: String::from(x.config.token_addr),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: info.sender.to_string(),
-            amount: amount,
-        }).unwrap(),
-        funds: Vec::new()
+    let wallet = deps.api.addr_validate(&wallet)?;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => x.config.owner.clone(),
     };
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    let users = x.users.get_mut(stage)
+        .ok_or(ContractError::InvalidStage{ stage: Uint128::new(stage as u128) })?;
+    let index = find_user(users, &wallet)?;
+    let user = &mut users[index];
 
-    Ok(Response::new()
-    .add_message(CosmosMsg::Wasm(bank_cw20))
-    .add_attribute("action", "Claim pending tokens"))
-}
-
-pub fn check_add_userinfo( users: &mut Vec<UserInfo>, wallet:Addr, amount: Uint128)
-{
-    let index =users.iter().position(|x| x.wallet_address == wallet);
-    if index == None {
-        users.push(UserInfo { 
-            wallet_address: wallet, 
-            total_amount: amount, 
-            released_amount: Uint128::zero(), 
-            pending_amount: Uint128::zero() 
-        });
+    settle_reward(user, reward_per_share)?;
+    let amount = user.total_amount.checked_sub(user.released_amount)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::NoPendingTokens{ });
     }
-    else{
-        users[index.unwrap()].total_amount += amount;
+    user.total_amount = user.released_amount;
+
+    //a stage that shares revoked tokens keeps them for the other participants
+    let shared = redistribute_revoked(&mut x, stage, amount, Some(index));
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    reset_reward_debt(&mut x.users[stage][index], reward_per_share);
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    if shared {
+        return Ok(Response::new()
+        .add_attribute("action", "Clawback")
+        .add_attribute("wallet", wallet)
+        .add_attribute("amount", amount)
+        .add_attribute("redistributed", amount));
     }
+
+    let token_addr = x.config.token_addr.as_str();
+    let token_amount = to_token_amount(&deps.querier, &x.config, amount)?;
+    spend_escrow(deps.storage, project_id, token_amount)?;
+
+    Ok(Response::new()
+    .add_message(cw20_message(token_addr, &Cw20ExecuteMsg::Transfer {
+        recipient: recipient.to_string(),
+        amount: token_amount,
+    })?)
+    .add_attribute("action", "Clawback")
+    .add_attribute("wallet", wallet)
+    .add_attribute("amount", amount))
 }
-pub fn try_adduser(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:Uint128, wallet:Addr, amount: Uint128)
+
+//moves part of an allocation to another stage together with the same share of the released amount,
+//from then on it vests under the parameters of the destination stage
+#[allow(clippy::too_many_arguments)]
+pub fn try_moveallocation(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128,
+    from_stage: Uint128, to_stage: Uint128, wallet: String, amount: Uint128)
     ->Result<Response, ContractError>
 {
     let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
@@ -220,11 +2488,71 @@ pub fn try_adduser(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:
         return Err(ContractError::Unauthorized{ });
     }
 
-    check_add_userinfo(&mut x.users[stage.u128() as usize], wallet, amount);
+    let wallet = deps.api.addr_validate(&wallet)?;
+    let from = from_stage.u128() as usize;
+    let to = to_stage.u128() as usize;
+    if from >= x.users.len() {
+        return Err(ContractError::InvalidStage{ stage: from_stage });
+    }
+    if from == to || to >= x.users.len() {
+        return Err(ContractError::InvalidStage{ stage: to_stage });
+    }
+    if amount == Uint128::zero() {
+        return Err(ContractError::ZeroAmount{ });
+    }
+
+    let index = find_user(&x.users[from], &wallet)?;
+    let source = &x.users[from][index];
+    if amount > source.total_amount {
+        return Err(ContractError::NotEnoughBalance{ });
+    }
+    if source.early_claimed || x.users[to].iter().any(|x| x.wallet_address == wallet && x.token_id.is_none() && x.early_claimed) {
+        return Err(ContractError::AllocationEarlyClaimed{ });
+    }
+    //amount is at most the non-zero total here
+    let moved_released = source.released_amount.multiply_ratio(amount, source.total_amount);
+
+    let reward_per_share = stage_reward_per_share(&x, from);
+    let source = &mut x.users[from][index];
+    settle_reward(source, reward_per_share)?;
+    source.total_amount = source.total_amount.checked_sub(amount)?;
+    source.released_amount = source.released_amount.checked_sub(moved_released)?;
+    reset_reward_debt(source, reward_per_share);
+    if source.total_amount == Uint128::zero() && source.reward_amount == Uint128::zero() {
+        x.users[from].remove(index);
+    }
+
+    let reward_per_share = stage_reward_per_share(&x, to);
+    check_add_userinfo(&mut x.users[to], wallet.clone(), amount, reward_per_share)?;
+    let index = find_user(&x.users[to], &wallet)?;
+    x.users[to][index].released_amount = x.users[to][index].released_amount.checked_add(moved_released)?;
+
     PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    let pending = calc_pending(deps.storage, _env, &x, x.users[to][index].clone(), to)?;
 
     Ok(Response::new()
-    .add_attribute("action", "Add  User info"))
+    .add_attribute("action", "Move allocation")
+    .add_attribute("wallet", wallet)
+    .add_attribute("moved_released", moved_released)
+    .add_attribute("pending", pending))
+}
+
+//allocated and released totals of a stage, the obligation is what the contract still owes
+pub fn calc_stage_stats(x: &ProjectInfo, stage: usize) -> Result<StageStats, ContractError>
+{
+    let reward_per_share = stage_reward_per_share(x, stage);
+    let mut stats = StageStats{
+        total_amount: Uint128::zero(),
+        released_amount: Uint128::zero(),
+        obligation: Uint128::zero(),
+    };
+    for user in x.users[stage].iter() {
+        stats.total_amount += user.total_amount;
+        stats.released_amount += user.released_amount;
+        stats.obligation += user.total_amount.saturating_sub(user.released_amount)
+            + calc_reward(user, reward_per_share)?;
+    }
+    Ok(stats)
 }
 
 pub fn try_setusers(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage:Uint128, user_infos: Vec<UserInfo>)
@@ -236,15 +2564,29 @@ pub fn try_setusers(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage
         return Err(ContractError::Unauthorized{ });
     }
 
-    x.users[stage.u128() as usize] = user_infos;
+    //positions only come from Tokenize, and a stage holding one can't be replaced underneath it
+    let current = x.users.get(stage.u128() as usize).ok_or(ContractError::InvalidStage{ stage })?;
+    if let Some(token_id) = current.iter().chain(user_infos.iter()).find_map(|user| user.token_id.clone()) {
+        return Err(ContractError::PositionTokenized{ token_id });
+    }
+
+    //imported users only share in tokens forfeited from now on
+    let stage = stage.u128() as usize;
+    let reward_per_share = stage_reward_per_share(&x, stage);
+    let mut user_infos = user_infos;
+    for user in user_infos.iter_mut() {
+        reset_reward_debt(user, reward_per_share);
+    }
+    x.users[stage] = user_infos;
 
+    check_escrow(deps.storage, &deps.querier, &x)?;
     PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
 
     Ok(Response::new()
     .add_attribute("action", "Set User infos for Seed stage"))
 }
 
-pub fn try_setprojectconfig(deps:DepsMut, info:MessageInfo,
+pub fn try_setprojectconfig(deps:DepsMut, _env: Env, info:MessageInfo,
     project_id: Uint128,
     admin: String, 
     token_addr: String,
@@ -258,7 +2600,12 @@ pub fn try_setprojectconfig(deps:DepsMut, info:MessageInfo,
         return Err(ContractError::Unauthorized{ });
     }
 
+    let now = Uint128::new(_env.block.time.seconds() as u128);
+    check_start_time_change(&x.config, start_time, now)?;
+    check_token_change(deps.storage, &x, &token_addr)?;
+
     x.config.owner = deps.api.addr_validate(admin.as_str())?;
+    x.config.decimals = Some(query_token_decimals(&deps.querier, &token_addr)?);
     x.config.token_addr = token_addr;
     x.config.start_time = start_time;
 
@@ -267,46 +2614,81 @@ pub fn try_setprojectconfig(deps:DepsMut, info:MessageInfo,
         .add_attribute("action", "SetConfig"))                                
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn try_addproject(deps:DepsMut, info:MessageInfo,
-    project_id: Uint128,
+    project_id: Option<Uint128>,
     admin: String, 
     token_addr: String,
     vesting_params: Vec<VestingParameter>,
-    start_time: Uint128
+    start_time: Uint128,
+    units: Option<AmountUnits>
 ) -> Result<Response, ContractError>
 {
-    //-----------check owner--------------------------
-    let owner = OWNER.load(deps.storage).unwrap();
-    if info.sender != owner {
-        return Err(ContractError::Unauthorized{});
-    }
+    //-----------launchpad projects pay the fee and belong to the sender--------
+    let owner = OWNER.load(deps.storage)?;
+    let mut messages = Vec::new();
+    let admin = if info.sender == owner {
+        deps.api.addr_validate(admin.as_str())?
+    } else {
+        let launchpad = LAUNCHPAD.may_load(deps.storage)?.ok_or(ContractError::Unauthorized{})?;
+        if let Some(fee) = &launchpad.fee {
+            messages = collect_fee(&info, fee, &launchpad.treasury)?;
+        }
+        info.sender.clone()
+    };
+
+    let project_id = match project_id {
+        Some(project_id) => {
+            if PROJECT_INFOS.has(deps.storage, project_id.u128().into()) {
+                return Err(ContractError::ProjectAlreadyExists{ project_id });
+            }
+            project_id
+        }
+        None => next_project_id(deps.storage)?,
+    };
 
+    let decimals = query_token_decimals(&deps.querier, &token_addr)?;
     let config: Config = Config{
-        owner: deps.api.addr_validate(admin.as_str())?,
-        token_addr : token_addr,
-        start_time : start_time,
+        owner: admin,
+        token_addr,
+        start_time,
+        send_targets: None,
+        decimals: Some(decimals),
+        units: units.unwrap_or_default(),
+        status: ProjectStatus::Active,
+        allowlist: None,
+        timelock: Uint128::zero(),
+        committee: None,
+        locked_token: false,
     };
 
     let mut _vesting_params = vesting_params;
-    if _vesting_params.len() == 0{
+    if _vesting_params.is_empty(){
         let sec_per_month = 60 * 60 * 24 * 30;
         let seed_param = VestingParameter {
             soon: Uint128::new(15), //15% unlock at tge
             after: Uint128::new(sec_per_month), //after 1 month
-            period: Uint128::new(sec_per_month * 6) //release over 6 month
+            period: Uint128::new(sec_per_month * 6), //release over 6 month
+            early_unlock: None,
+            redistribute_revoked: false
         };
         let presale_param = VestingParameter {
             soon: Uint128::new(20), //20% unlock at tge
             after: Uint128::new(sec_per_month), //ater 1 month
-            period: Uint128::new(sec_per_month * 5) //release over 5 month
+            period: Uint128::new(sec_per_month * 5), //release over 5 month
+            early_unlock: None,
+            redistribute_revoked: false
         };
         let ido_param = VestingParameter {
             soon: Uint128::new(25), //25% unlock at tge
             after: Uint128::new(sec_per_month), //after 1 month
-            period: Uint128::new(sec_per_month * 4) //release over 4 month
+            period: Uint128::new(sec_per_month * 4), //release over 4 month
+            early_unlock: None,
+            redistribute_revoked: false
         };
         _vesting_params = vec![seed_param, presale_param, ido_param];
     }
+    validate_vesting_parameters(deps.api, &_vesting_params)?;
 
     let mut users = Vec::new();
     for _ in _vesting_params.clone(){
@@ -314,25 +2696,122 @@ pub fn try_addproject(deps:DepsMut, info:MessageInfo,
     }
 
     let project_info: ProjectInfo = ProjectInfo{
-        project_id: project_id,
-        config: config,
+        project_id,
+        config,
         vest_param: _vesting_params,
-        users: users,
+        users,
+        milestones: Vec::new(),
+        milestone_voting: None,
+        reward_per_share: Vec::new(),
     };
 
     PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &project_info)?;
 
     Ok(Response::new()
-        .add_attribute("action", "add project"))                                
+        .add_messages(messages)
+        .add_attribute("action", "add project")
+        .add_attribute("project_id", project_id)
+        .set_data(to_binary(&project_id)?))
+}
+
+//native fees are forwarded and any surplus goes back, cw20 fees are pulled from the sender.
+//coins the fee is not paid in are refused
+pub fn collect_fee(info: &MessageInfo, fee: &ProjectFee, treasury: &str) -> Result<Vec<CosmosMsg>, ContractError>
+{
+    match fee {
+        ProjectFee::Native{ denom, amount } => {
+            if let Some(coin) = info.funds.iter().find(|coin| &coin.denom != denom) {
+                return Err(ContractError::UnexpectedFunds{ denom: coin.denom.clone() });
+            }
+            let paid = info.funds.iter().fold(Uint128::zero(), |sum, coin| sum + coin.amount);
+            if paid < *amount {
+                return Err(ContractError::FeeNotPaid{ });
+            }
+            let mut messages = vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: treasury.to_string(),
+                amount: vec![Coin::new(amount.u128(), denom)],
+            })];
+            if paid > *amount {
+                messages.push(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: vec![Coin::new((paid - *amount).u128(), denom)],
+                }));
+            }
+            Ok(messages)
+        }
+        ProjectFee::Cw20{ token_addr, amount } => {
+            if let Some(coin) = info.funds.first() {
+                return Err(ContractError::UnexpectedFunds{ denom: coin.denom.clone() });
+            }
+            Ok(vec![cw20_message(token_addr, &Cw20ExecuteMsg::TransferFrom {
+                owner: info.sender.to_string(),
+                recipient: treasury.to_string(),
+                amount: *amount,
+            })?])
+        }
+    }
+}
+
+pub fn try_setlaunchpad(deps: DepsMut, info: MessageInfo, launchpad: Option<LaunchpadConfig>)
+    -> Result<Response, ContractError>
+{
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized{});
+    }
+
+    match launchpad {
+        Some(launchpad) => {
+            deps.api.addr_validate(&launchpad.treasury)?;
+            match &launchpad.fee {
+                Some(ProjectFee::Native{ amount, .. }) | Some(ProjectFee::Cw20{ amount, .. })
+                    if *amount == Uint128::zero() => return Err(ContractError::InvalidLaunchpadFee{ }),
+                Some(ProjectFee::Cw20{ token_addr, .. }) => { deps.api.addr_validate(token_addr)?; }
+                _ => {}
+            }
+            LAUNCHPAD.save(deps.storage, &launchpad)?;
+        }
+        None => LAUNCHPAD.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "Set launchpad"))
+}
+
+pub fn try_setprojectstatus(deps: DepsMut, info: MessageInfo, project_id: Uint128, status: ProjectStatus)
+    -> Result<Response, ContractError>
+{
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized{});
+    }
+
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    x.config.status = status;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "Set project status"))
+}
+
+//explicitly numbered projects may already hold ids past the counter, those are skipped
+pub fn next_project_id(store: &mut dyn Storage) -> StdResult<Uint128>
+{
+    let mut project_id = NEXT_PROJECT_ID.may_load(store)?.unwrap_or_else(|| Uint128::new(1));
+    while PROJECT_INFOS.has(store, project_id.u128().into()) {
+        project_id += Uint128::new(1);
+    }
+    NEXT_PROJECT_ID.save(store, &(project_id + Uint128::new(1)))?;
+    Ok(project_id)
 }
 pub fn try_setconfig(deps:DepsMut, info:MessageInfo, admin: String) 
     -> Result<Response, ContractError>
 {
-    // //-----------check owner--------------------------
-    // let owner = OWNER.load(deps.storage).unwrap();
-    // if info.sender != owner {
-    //     return Err(ContractError::Unauthorized{});
-    // }
+    //-----------check owner--------------------------
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized{});
+    }
 
     let admin_addr = deps.api.addr_validate(&admin).unwrap();
     OWNER.save(deps.storage, &admin_addr)?;
@@ -340,3 +2819,253 @@ pub fn try_setconfig(deps:DepsMut, info:MessageInfo, admin: String)
     Ok(Response::new()
         .add_attribute("action", "SetConfig"))                                
 }
+
+pub fn try_setlockedtoken(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, enabled: bool)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner && info.sender != x.config.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    //holders aren't tracked while disabled, so enabling starts from a full rebuild
+    let rebuild = enabled && !x.config.locked_token;
+    x.config.locked_token = enabled;
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+    if rebuild {
+        resync_voting_power(deps.storage, &_env, project_id)?;
+    }
+
+    Ok(Response::new()
+    .add_attribute("action", "Set locked token"))
+}
+
+pub fn try_setdefaultlockedtoken(deps: DepsMut, info: MessageInfo, project_id: Option<Uint128>)
+    ->Result<Response, ContractError>
+{
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+
+    match project_id {
+        Some(project_id) => {
+            let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+            if !x.config.locked_token {
+                return Err(ContractError::LockedTokenDisabled{ project_id });
+            }
+            DEFAULT_LOCKED_TOKEN.save(deps.storage, &project_id)?;
+        }
+        None => DEFAULT_LOCKED_TOKEN.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+    .add_attribute("action", "Set default locked token"))
+}
+
+//------------Position NFT---------------------------------------
+pub fn try_tokenize(deps: DepsMut, info: MessageInfo, project_id: Uint128, stage: Uint128)
+    ->Result<Response, ContractError>
+{
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_not_frozen(deps.storage, project_id, &info.sender)?;
+    let users = x.users.get_mut(stage.u128() as usize)
+        .ok_or(ContractError::InvalidStage{ stage })?;
+    let index = find_user(users, &info.sender)?;
+    let user = &mut users[index];
+
+    let next = NEXT_TOKEN_ID.may_load(deps.storage)?.unwrap_or_default();
+    NEXT_TOKEN_ID.save(deps.storage, &(next + 1))?;
+    let token_id = next.to_string();
+    user.token_id = Some(token_id.clone());
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    let position = Position{
+        owner: info.sender.clone(),
+        approvals: Vec::new(),
+        project_id,
+        stage,
+        wallet: info.sender,
+    };
+    POSITIONS.save(deps.storage, &token_id, &position)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "mint")
+    .add_attribute("minter", position.owner)
+    .add_attribute("token_id", token_id))
+}
+
+pub fn try_claimposition(deps: DepsMut, _env: Env, info: MessageInfo, project_id: Uint128, token_id: String,
+    recipient: Option<String>)
+    ->Result<Response, ContractError>
+{
+    let position = POSITIONS.may_load(deps.storage, &token_id)?
+        .filter(|position| position.project_id == project_id)
+        .ok_or(ContractError::PositionNotFound{ token_id: token_id.clone() })?;
+    if info.sender != position.owner {
+        return Err(ContractError::Unauthorized{ });
+    }
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => position.owner.clone(),
+    };
+
+    let mut x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    check_allowed(deps.storage, &deps.querier, &x, &position.owner)?;
+    check_not_frozen(deps.storage, project_id, &position.wallet)?;
+    check_not_frozen(deps.storage, project_id, &position.owner)?;
+
+    let stage = position.stage.u128() as usize;
+    let index = x.users[stage].iter().position(|user| user.token_id.as_ref() == Some(&token_id))
+        .ok_or(ContractError::PositionNotFound{ token_id: token_id.clone() })?;
+    let amount = release_user_pending(deps.storage, &_env, &mut x, stage, index)?;
+    if amount == Uint128::zero() {
+        return Err(ContractError::NoPendingTokens{});
+    }
+    PROJECT_INFOS.save(deps.storage, project_id.u128().into(), &x)?;
+
+    let token_addr = x.config.token_addr.as_str();
+    let amount = to_token_amount(&deps.querier, &x.config, amount)?;
+    spend_escrow(deps.storage, project_id, amount)?;
+
+    Ok(Response::new()
+    .add_message(cw20_message(token_addr, &Cw20ExecuteMsg::Transfer {
+        recipient: recipient.to_string(),
+        amount,
+    })?)
+    .add_attribute("action", "Claim position")
+    .add_attribute("token_id", token_id)
+    .add_attribute("amount", amount))
+}
+
+//the owner, an operator of the owner or an approved spender may move a position
+pub fn check_can_send(deps: Deps, _env: &Env, sender: &Addr, position: &Position) -> Result<(), ContractError>
+{
+    if *sender == position.owner {
+        return Ok(());
+    }
+    if position.approvals.iter().any(|approval| approval.spender == sender.as_str()
+        && !approval.expires.is_expired(&_env.block)) {
+        return Ok(());
+    }
+    check_operator(deps, _env, sender, position)
+}
+
+pub fn check_operator(deps: Deps, _env: &Env, sender: &Addr, position: &Position) -> Result<(), ContractError>
+{
+    if *sender == position.owner {
+        return Ok(());
+    }
+    match NFT_OPERATORS.may_load(deps.storage, (&position.owner, sender))? {
+        Some(expires) if !expires.is_expired(&_env.block) => Ok(()),
+        _ => Err(ContractError::Unauthorized{ }),
+    }
+}
+
+pub fn transfer_position(deps: DepsMut, _env: &Env, sender: &Addr, recipient: &str, token_id: &str)
+    -> Result<Position, ContractError>
+{
+    let mut position = POSITIONS.may_load(deps.storage, token_id)?
+        .ok_or(ContractError::PositionNotFound{ token_id: token_id.to_string() })?;
+    check_can_send(deps.as_ref(), _env, sender, &position)?;
+    position.owner = deps.api.addr_validate(recipient)?;
+    position.approvals = Vec::new();
+    POSITIONS.save(deps.storage, token_id, &position)?;
+    Ok(position)
+}
+
+pub fn try_transfernft(deps: DepsMut, _env: Env, info: MessageInfo, recipient: String, token_id: String)
+    ->Result<Response, ContractError>
+{
+    transfer_position(deps, &_env, &info.sender, &recipient, &token_id)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "transfer_nft")
+    .add_attribute("sender", info.sender)
+    .add_attribute("recipient", recipient)
+    .add_attribute("token_id", token_id))
+}
+
+pub fn try_sendnft(deps: DepsMut, _env: Env, info: MessageInfo, contract: String, token_id: String, msg: Binary)
+    ->Result<Response, ContractError>
+{
+    transfer_position(deps, &_env, &info.sender, &contract, &token_id)?;
+
+    let hook = Cw721ReceiveMsg{
+        sender: info.sender.to_string(),
+        token_id: token_id.clone(),
+        msg,
+    };
+    Ok(Response::new()
+    .add_message(hook.into_cosmos_msg(contract.clone())?)
+    .add_attribute("action", "send_nft")
+    .add_attribute("sender", info.sender)
+    .add_attribute("recipient", contract)
+    .add_attribute("token_id", token_id))
+}
+
+pub fn try_approvenft(deps: DepsMut, _env: Env, info: MessageInfo, spender: String, token_id: String,
+    expires: Option<Expiration>)
+    ->Result<Response, ContractError>
+{
+    let mut position = POSITIONS.may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::PositionNotFound{ token_id: token_id.clone() })?;
+    check_operator(deps.as_ref(), &_env, &info.sender, &position)?;
+
+    let spender = deps.api.addr_validate(&spender)?.to_string();
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&_env.block) {
+        return Err(ContractError::Unauthorized{ });
+    }
+    position.approvals.retain(|approval| approval.spender != spender);
+    position.approvals.push(NftApproval{ spender: spender.clone(), expires });
+    POSITIONS.save(deps.storage, &token_id, &position)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "approve")
+    .add_attribute("spender", spender)
+    .add_attribute("token_id", token_id))
+}
+
+pub fn try_revokenft(deps: DepsMut, _env: Env, info: MessageInfo, spender: String, token_id: String)
+    ->Result<Response, ContractError>
+{
+    let mut position = POSITIONS.may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::PositionNotFound{ token_id: token_id.clone() })?;
+    check_operator(deps.as_ref(), &_env, &info.sender, &position)?;
+
+    position.approvals.retain(|approval| approval.spender != spender);
+    POSITIONS.save(deps.storage, &token_id, &position)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "revoke")
+    .add_attribute("spender", spender)
+    .add_attribute("token_id", token_id))
+}
+
+pub fn try_approveall(deps: DepsMut, _env: Env, info: MessageInfo, operator: String, expires: Option<Expiration>)
+    ->Result<Response, ContractError>
+{
+    let operator = deps.api.addr_validate(&operator)?;
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&_env.block) {
+        return Err(ContractError::Unauthorized{ });
+    }
+    NFT_OPERATORS.save(deps.storage, (&info.sender, &operator), &expires)?;
+
+    Ok(Response::new()
+    .add_attribute("action", "approve_all")
+    .add_attribute("operator", operator))
+}
+
+pub fn try_revokeall(deps: DepsMut, info: MessageInfo, operator: String)
+    ->Result<Response, ContractError>
+{
+    let operator = deps.api.addr_validate(&operator)?;
+    NFT_OPERATORS.remove(deps.storage, (&info.sender, &operator));
+
+    Ok(Response::new()
+    .add_attribute("action", "revoke_all")
+    .add_attribute("operator", operator))
+}
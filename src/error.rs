@@ -1,12 +1,14 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, OverflowError, Uint128};
 use thiserror::Error;
-use cosmwasm_std::{Uint128};
 
 #[derive(Error, Debug)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -16,14 +18,182 @@ pub enum ContractError {
     #[error("There is no enough tokens")]
     NotEnoughBalance {},
 
-    #[error("Not correct Milestone status : {aust_balance} {estimate_exchange_rate} {epoch_exchange_rate} {withdraw_amount} {release_amount}")]
-    Testing{
-        aust_balance: String,
-        estimate_exchange_rate: String,
-        epoch_exchange_rate: String,
-        withdraw_amount: String,
-        release_amount: String
-    }
+    #[error("Claim grant does not allow another recipient")]
+    RecipientNotAllowed {},
+
+    #[error("Claimed tokens may not be sent to {contract}")]
+    SendTargetNotAllowed { contract: String },
+
+    #[error("Invalid stage: {stage}")]
+    InvalidStage { stage: Uint128 },
+
+    #[error("Stage {stage} still holds allocations or milestones")]
+    StageNotEmpty { stage: Uint128 },
+
+    #[error("Amount must not be zero")]
+    ZeroAmount {},
+
+    #[error("Allocation was already claimed early")]
+    AllocationEarlyClaimed {},
+
+    #[error("User not found: {wallet}")]
+    UserNotFound { wallet: String },
+
+    #[error("Allocation can not go below the released amount {released}")]
+    AllocationBelowReleased { released: Uint128 },
+
+    #[error("Wallet appears more than once: {wallet}")]
+    DuplicateWallet { wallet: String },
+
+    #[error("Release has not started yet")]
+    ReleaseNotStarted {},
+
+    #[error("Invalid vesting parameter")]
+    InvalidVestingParameter {},
+
+    #[error("Invalid sale")]
+    InvalidSale {},
+
+    #[error("No open sale on stage {stage}")]
+    SaleNotOpen { stage: Uint128 },
+
+    #[error("Sale on stage {stage} did not succeed")]
+    SaleNotSucceeded { stage: Uint128 },
+
+    #[error("Sale on stage {stage} did not fail")]
+    SaleNotFailed { stage: Uint128 },
+
+    #[error("Nothing to withdraw")]
+    NothingToWithdraw {},
+
+    #[error("Not on the sale whitelist")]
+    NotWhitelisted {},
+
+    #[error("Purchase outside the allowed amount per wallet")]
+    PurchaseOutOfRange {},
+
+    #[error("Operation is timelocked, queue it")]
+    OperationTimelocked {},
+
+    #[error("Operation can't be queued")]
+    InvalidOperation {},
+
+    #[error("Operation {operation_id} not found")]
+    OperationNotFound { operation_id: Uint128 },
+
+    #[error("Operation {operation_id} is not executable yet")]
+    OperationNotReady { operation_id: Uint128 },
+
+    #[error("Invalid committee")]
+    InvalidCommittee {},
+
+    #[error("Not a committee member")]
+    NotCommitteeMember {},
+
+    #[error("Proposal {proposal_id} not found")]
+    ProposalNotFound { proposal_id: Uint128 },
+
+    #[error("Proposal {proposal_id} is closed")]
+    ProposalClosed { proposal_id: Uint128 },
+
+    #[error("Proposal {proposal_id} is already approved by the sender")]
+    AlreadyApproved { proposal_id: Uint128 },
+
+    #[error("Proposal {proposal_id} has not enough approvals")]
+    ThresholdNotReached { proposal_id: Uint128 },
+
+    #[error("Project {project_id} has no locked token")]
+    LockedTokenDisabled { project_id: Uint128 },
+
+    #[error("No project given and no default locked token set")]
+    NoDefaultLockedToken {},
+
+    #[error("Locked tokens can only be queried, they unlock through claims")]
+    LockedTokenNotTransferable {},
+
+    #[error("Position {token_id} not found")]
+    PositionNotFound { token_id: String },
+
+    #[error("Allocation is already tokenized as {token_id}")]
+    PositionTokenized { token_id: String },
+
+    #[error("User {wallet} is frozen")]
+    UserFrozen { wallet: String },
+
+    #[error("Wallet {wallet} is not allowed")]
+    WalletNotAllowed { wallet: String },
+
+    #[error("Project creation fee not paid")]
+    FeeNotPaid {},
+
+    #[error("Project creation fee must not be zero")]
+    InvalidLaunchpadFee {},
+
+    #[error("Project {project_id} is paused")]
+    ProjectPaused { project_id: Uint128 },
+
+    #[error("Project {project_id} already exists")]
+    ProjectAlreadyExists { project_id: Uint128 },
+
+    #[error("The token can't change while the project holds an escrow")]
+    EscrowNotEmpty {},
+
+    #[error("Unexpected funds: {denom}")]
+    UnexpectedFunds { denom: String },
+
+    #[error("Not a cw20 token: {token_addr}")]
+    InvalidToken { token_addr: String },
+
+    #[error("Release has already started")]
+    ReleaseAlreadyStarted {},
+
+    #[error("The number of stages can not change after the release started")]
+    StageCountChanged {},
+
+    #[error("The unlock at release can not be reduced after the release started")]
+    ImmediateUnlockReduced {},
+
+    #[error("The cliff can not be extended after the release started")]
+    CliffExtended {},
+
+    #[error("The vesting period can not be extended after the release started")]
+    PeriodExtended {},
+
+    #[error("The early claim penalty can not be increased after the release started")]
+    PenaltyIncreased {},
+
+    #[error("The release start can not be delayed after the release started")]
+    StartTimeDelayed {},
+
+    #[error("Milestone not found: {name}")]
+    MilestoneNotFound { name: String },
+
+    #[error("Milestone already exists: {name}")]
+    MilestoneExists { name: String },
+
+    #[error("Milestone has already been decided: {name}")]
+    MilestoneAlreadyDecided { name: String },
+
+    #[error("Milestone percentages exceed 100")]
+    InvalidMilestonePercent {},
+
+    #[error("Milestone voting is not enabled for this project")]
+    MilestoneVotingDisabled {},
+
+    #[error("Milestones of this project are decided by investor vote")]
+    MilestoneVotingEnabled {},
+
+    #[error("Milestone vote is not open: {name}")]
+    MilestoneVoteNotOpen { name: String },
+
+    #[error("Milestone vote is still open: {name}")]
+    MilestoneVoteOpen { name: String },
+
+    #[error("Already voted on milestone: {name}")]
+    AlreadyVoted { name: String },
+
+    #[error("No voting power")]
+    NoVotingPower {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
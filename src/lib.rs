@@ -1,3 +1,5 @@
+#![allow(non_snake_case)]
+
 pub mod contract;
 pub mod query;
 mod error;
@@ -9,5 +11,5 @@ pub use crate::error::ContractError;
 #[cfg(test)]
 mod test;
 
-// #[cfg(test)]
-// mod mock_querier;
+#[cfg(test)]
+mod mock_querier;
@@ -1,13 +1,17 @@
-use cosmwasm_std::{Uint128, Addr};
+use cosmwasm_std::{Uint128, Addr, Decimal, Binary, CosmosMsg, WasmMsg, StdResult, to_binary};
+use cw20::{Cw20ReceiveMsg, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cw_storage_plus::{Map, U128Key};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub admin: Option<String>,
 }
 
+//credits projects created before escrows existed with what they still owe, as far as the contract holds their token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
@@ -15,11 +19,12 @@ pub enum ExecuteMsg {
         admin: String,
     },
     AddProject {
-        project_id: Uint128,
-        admin: String, 
+        project_id: Option<Uint128>, //None to take the next free id
+        admin: String, //ignored for launchpad projects, the sender owns them
         token_addr: String,
         vesting_params: Vec<VestingParameter>,
-        start_time: Uint128 
+        start_time: Uint128,
+        units: Option<AmountUnits>
     },
     StartRelease{
         project_id: Uint128,
@@ -27,7 +32,7 @@ pub enum ExecuteMsg {
     },
     SetProjectInfo{
         project_id: Uint128,
-        project_info: ProjectInfo
+        project_info: ProjectInfo //users, reward_per_share and the status, committee, timelock, send_targets, allowlist and locked_token of the config are ignored, the stored ones are kept
     },
     SetProjectConfig { 
         project_id: Uint128,
@@ -45,13 +50,257 @@ pub enum ExecuteMsg {
         wallet: Addr,
         amount: Uint128,
     },
+    AddUsers {
+        project_id: Uint128,
+        stage: Uint128,
+        users: Vec<UserAllocation> //added on top of existing allocations
+    },
+    SetAllocation {
+        project_id: Uint128,
+        stage: Uint128,
+        wallet: String,
+        amount: Uint128 //new total amount, not below the released amount
+    },
+    MoveAllocation {
+        project_id: Uint128,
+        from_stage: Uint128,
+        to_stage: Uint128,
+        wallet: String,
+        amount: Uint128
+    },
+    RemoveUser {
+        project_id: Uint128,
+        stage: Uint128,
+        wallet: String
+    },
     SetUsers {
         project_id: Uint128,
         stage: Uint128,
         user_infos: Vec<UserInfo>
     },
     ClaimPendingTokens{
+        project_id: Uint128,
+        beneficiary: Option<String>, //wallet to claim for, the sender by default
+        recipient: Option<String> //where the tokens go, the beneficiary by default
+    },
+    ClaimAndSend{
+        project_id: Uint128,
+        contract: String, //receives the claimed tokens through cw20 Send
+        msg: Binary, //hook message passed on to the contract
+        beneficiary: Option<String>
+    },
+    Distribute{
+        project_id: Uint128,
+        stage: Uint128,
+        limit: u32 //number of users paid out in this transaction
+    },
+    SetSendTargets{
+        project_id: Uint128,
+        targets: Option<Vec<String>> //None allows any contract
+    },
+    GrantClaim{
+        project_id: Uint128,
+        grantee: String,
+        allow_recipient: bool //grantee may send the tokens somewhere else than the beneficiary
+    },
+    RevokeClaimGrant{
+        project_id: Uint128,
+        grantee: String
+    },
+    EarlyClaim{
         project_id: Uint128
+    },
+    AddMilestone{
+        project_id: Uint128,
+        stage: Uint128, //team tranche released by the milestone
+        name: String,
+        percent: Uint128,
+        period: Uint128
+    },
+    ApproveMilestone{
+        project_id: Uint128,
+        name: String
+    },
+    RejectMilestone{
+        project_id: Uint128,
+        name: String
+    },
+    SetMilestoneVoting{
+        project_id: Uint128,
+        voting: Option<VotingConfig>
+    },
+    StartMilestoneVote{
+        project_id: Uint128,
+        name: String
+    },
+    VoteMilestone{
+        project_id: Uint128,
+        name: String,
+        approve: bool
+    },
+    TallyMilestoneVote{
+        project_id: Uint128,
+        name: String
+    },
+    SetLaunchpad{
+        launchpad: Option<LaunchpadConfig> //None allows only the owner to add projects
+    },
+    SetProjectStatus{
+        project_id: Uint128,
+        status: ProjectStatus
+    },
+    SetSale{
+        project_id: Uint128,
+        stage: Uint128,
+        sale: Option<SaleConfig> //None closes the sale
+    },
+    Purchase{
+        project_id: Uint128,
+        stage: Uint128
+    },
+    Refund{
+        project_id: Uint128,
+        stage: Uint128
+    },
+    WithdrawRaised{
+        project_id: Uint128,
+        stage: Uint128
+    },
+    SetAllowlist{
+        project_id: Uint128,
+        allowlist: Option<AllowlistConfig> //None lets every wallet in
+    },
+    UpdateAllowlist{
+        project_id: Uint128,
+        add: Vec<String>,
+        remove: Vec<String>
+    },
+    FreezeUser{
+        project_id: Uint128,
+        wallet: String
+    },
+    UnfreezeUser{
+        project_id: Uint128,
+        wallet: String
+    },
+    Clawback{
+        project_id: Uint128,
+        stage: Uint128,
+        wallet: String,
+        recipient: Option<String> //the project admin by default
+    },
+    SetTimelock{
+        project_id: Uint128,
+        delay: Uint128 //seconds, a shorter delay has to wait out the current one
+    },
+    QueueOperation{
+        project_id: Uint128,
+        msg: Box<ExecuteMsg>
+    },
+    ExecuteOperation{
+        project_id: Uint128,
+        operation_id: Uint128
+    },
+    CancelOperation{
+        project_id: Uint128,
+        operation_id: Uint128
+    },
+    SetCommittee{
+        project_id: Uint128,
+        committee: Option<Committee> //the committee becomes the project admin
+    },
+    Propose{
+        project_id: Uint128,
+        msg: Box<ExecuteMsg> //runs with the project admin authority once approved
+    },
+    ApproveProposal{
+        project_id: Uint128,
+        proposal_id: Uint128
+    },
+    ExecuteProposal{
+        project_id: Uint128,
+        proposal_id: Uint128
+    },
+    SetLockedToken{
+        project_id: Uint128,
+        enabled: bool //serve the locked balances through the cw20 queries
+    },
+    SetDefaultLockedToken{
+        project_id: Option<Uint128> //answers cw20 queries that name no project
+    },
+    Tokenize{
+        project_id: Uint128,
+        stage: Uint128 //mints the sender's allocation of the stage as a position NFT
+    },
+    ClaimPosition{
+        project_id: Uint128,
+        token_id: String,
+        recipient: Option<String> //the NFT owner by default
+    },
+    Receive(Cw20ReceiveMsg), //project tokens sent with a ReceiveMsg
+    WithdrawEscrow{
+        project_id: Uint128,
+        amount: Uint128, //token base units, only what the allocations don't need
+        recipient: Option<String> //the sender by default
+    },
+
+    //------------locked token, cw20 shaped, all refused---------------------------------------
+    Transfer{
+        recipient: String,
+        amount: Uint128
+    },
+    Send{
+        contract: String,
+        amount: Uint128,
+        msg: Binary
+    },
+    IncreaseAllowance{
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>
+    },
+    DecreaseAllowance{
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>
+    },
+    TransferFrom{
+        owner: String,
+        recipient: String,
+        amount: Uint128
+    },
+    SendFrom{
+        owner: String,
+        contract: String,
+        amount: Uint128,
+        msg: Binary
+    },
+
+    //------------cw721---------------------------------------
+    TransferNft{
+        recipient: String,
+        token_id: String
+    },
+    SendNft{
+        contract: String,
+        token_id: String,
+        msg: Binary
+    },
+    Approve{
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>
+    },
+    Revoke{
+        spender: String,
+        token_id: String
+    },
+    ApproveAll{
+        operator: String,
+        expires: Option<Expiration>
+    },
+    RevokeAll{
+        operator: String
     }
 }
 
@@ -64,7 +313,40 @@ pub enum QueryMsg {
     GetBalance { project_id: Uint128, wallet: String },
     GetProjectInfo { project_id: Uint128 },
     GetAllProjectInfo {},
-    GetOwner{ }
+    GetOwner{ },
+    GetMilestoneTally { project_id: Uint128, name: String },
+    GetClaimGrants { project_id: Uint128, wallet: String },
+    GetProjectStats { project_id: Uint128 },
+    GetProjectsByAdmin { admin: String },
+    GetLaunchpad {},
+    GetSale { project_id: Uint128, stage: Uint128 },
+    GetSaleStatus { project_id: Uint128, stage: Uint128 },
+    IsAllowed { project_id: Uint128, wallet: String },
+    GetPendingOperations { project_id: Uint128 },
+    GetProposals { project_id: Uint128 },
+    GetEscrow { project_id: Uint128 },
+
+    //------------locked token, cw20 shaped---------------------------------------
+    //query only, the balance shrinks as tokens unlock and can't be moved or approved.
+    //project_id falls back to the default locked token, there is no fallback when none is set
+    Balance { address: String, project_id: Option<Uint128> },
+    TokenInfo { project_id: Option<Uint128> },
+    Allowance { owner: String, spender: String, project_id: Option<Uint128> }, //always zero
+
+    //------------voting power, dao voting module shaped---------------------------------------
+    //unreleased allocations of projects with a locked token, in token base units
+    VotingPowerAtHeight { wallet: String, height: Option<u64>, project_id: Option<Uint128> },
+    TotalPowerAtHeight { height: Option<u64>, project_id: Option<Uint128> },
+
+    //------------cw721---------------------------------------
+    OwnerOf { token_id: String, include_expired: Option<bool> },
+    ApprovedForAll { owner: String, include_expired: Option<bool>, start_after: Option<String>, limit: Option<u32> },
+    NumTokens {},
+    ContractInfo {},
+    NftInfo { token_id: String },
+    AllNftInfo { token_id: String, include_expired: Option<bool> },
+    Tokens { owner: String, start_after: Option<String>, limit: Option<u32> },
+    AllTokens { start_after: Option<String>, limit: Option<u32> }
 }
 
 //------------Config---------------------------------------
@@ -73,14 +355,133 @@ pub struct Config {
     pub owner: Addr,
     pub token_addr: String,
 	pub start_time: Uint128,
+	#[serde(default)]
+	pub send_targets: Option<Vec<Addr>>, //contracts claimed tokens may be sent to, None for any
+	#[serde(default)]
+	pub decimals: Option<u8>, //cached from the cw20 TokenInfo, None for projects created before caching
+	#[serde(default)]
+	pub units: AmountUnits, //unit of every allocation, claim and query amount of the project
+	#[serde(default)]
+	pub status: ProjectStatus,
+	#[serde(default)]
+	pub allowlist: Option<AllowlistConfig>, //None lets every wallet in
+	#[serde(default)]
+	pub timelock: Uint128, //seconds sensitive operations wait in the queue, zero runs them at once
+	#[serde(default)]
+	pub committee: Option<Committee>, //set while the owner is the contract itself acting on proposals
+	#[serde(default)]
+	pub locked_token: bool, //locked balances are served through the cw20 queries
+}
+
+//------------Committee---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Committee{
+	pub members: Vec<Addr>,
+	pub threshold: u32, //approvals a proposal needs
+	pub expiry: Uint128, //seconds a proposal stays open
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal{
+	pub proposal_id: Uint128,
+	pub msg: ExecuteMsg,
+	pub proposer: Addr,
+	pub approvals: Vec<Addr>,
+	pub expires: Uint128,
+	pub executed: bool,
+}
+
+//------------Timelock---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Operation{
+	pub operation_id: Uint128,
+	pub msg: ExecuteMsg,
+	pub proposer: Addr, //the operation runs with the authority of the proposer
+	pub eta: Uint128, //earliest execution time
+}
+
+//------------Allowlist---------------------------------------
+//wallets on the local allowlist are always approved, the others are asked from the registry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllowlistConfig{
+	pub registry: Option<String>, //contract answering RegistryQueryMsg
+}
+
+//hook of the cw20 Send a project token is funded with
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg{
+	Deposit{ project_id: Uint128 }, //adds the tokens to the escrow of the project
+}
+
+//query interface an external allowlist registry has to answer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryQueryMsg{
+	IsAllowed{ address: String }, //returns IsAllowedResponse
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsAllowedResponse{
+	pub allowed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectStatus{
+	#[default]
+	Active,
+	Paused, //only the owner may act on the project
+	Delisted, //paused and left out of project listings
+}
+
+//------------Launchpad---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LaunchpadConfig{
+	pub fee: Option<ProjectFee>, //paid by anyone but the owner to add a project
+	pub treasury: String, //receives the fees
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectFee{
+	Native{ denom: String, amount: Uint128 },
+	Cw20{ token_addr: String, amount: Uint128 }, //taken through TransferFrom, needs an allowance
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountUnits{
+	Raw, //base units of the cw20 token
+	#[default]
+	WholeTokens, //whole tokens, multiplied by 10^decimals when paid out
 }
 
 //------------Vesting parameter---------------------------------------
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Copy)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct VestingParameter{
 	pub soon: Uint128,
 	pub after: Uint128,
-	pub period: Uint128
+	pub period: Uint128,
+	#[serde(default)]
+	pub early_unlock: Option<EarlyUnlock>, //allows to exit the stage before the end of the schedule
+	#[serde(default)]
+	pub redistribute_revoked: bool, //tokens taken back by RemoveUser, a lower SetAllocation or Clawback go to the rest of the stage
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EarlyUnlock{
+	pub penalty: Uint128, //percent of the locked amount kept back on an early claim
+	pub decay: bool, //shrink the penalty linearly to zero over the schedule
+	pub destination: PenaltyDestination,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PenaltyDestination{
+	Burn,
+	Treasury{ address: String },
+	Redistribute, //shared between the remaining participants of the stage
 }
 
 //-------------Token holder-------------------------------------------
@@ -90,6 +491,223 @@ pub struct UserInfo{
 	pub total_amount: Uint128, //WFD token total amount that the investor buys.
 	pub released_amount: Uint128, //released WFD token amount of totalAmount
 	pub pending_amount: Uint128, //token amount that investor can claim 
+	#[serde(default)]
+	pub early_claimed: bool, //locked amount was released early with a penalty
+	#[serde(default)]
+	pub reward_debt: Uint128, //forfeited tokens per share already accounted for
+	#[serde(default)]
+	pub reward_amount: Uint128, //share of forfeited tokens accrued but not claimed yet
+	#[serde(default)]
+	pub frozen: bool, //claims are on hold, filled in by GetUserInfo
+	#[serde(default)]
+	pub token_id: Option<String>, //position NFT whose owner claims instead of the wallet
+}
+
+//------------Position NFT---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Position{
+	pub owner: Addr,
+	pub approvals: Vec<NftApproval>,
+	pub project_id: Uint128,
+	pub stage: Uint128,
+	pub wallet: Addr, //wallet the allocation is recorded under
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftApproval{
+	pub spender: String,
+	pub expires: Expiration,
+}
+
+//nft extension, the amounts are read from the allocation at query time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionExtension{
+	pub project_id: Uint128,
+	pub stage: Uint128,
+	pub total_amount: Uint128,
+	pub released_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingPowerAtHeightResponse{
+	pub power: Uint128,
+	pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalPowerAtHeightResponse{
+	pub power: Uint128,
+	pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerOfResponse{
+	pub owner: String,
+	pub approvals: Vec<NftApproval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ApprovedForAllResponse{
+	pub operators: Vec<NftApproval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NumTokensResponse{
+	pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractInfoResponse{
+	pub name: String,
+	pub symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftInfoResponse{
+	pub token_uri: Option<String>,
+	pub extension: PositionExtension,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllNftInfoResponse{
+	pub access: OwnerOfResponse,
+	pub info: NftInfoResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokensResponse{
+	pub tokens: Vec<String>,
+}
+
+//hook sent along with SendNft
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721ReceiveMsg{
+	pub sender: String,
+	pub token_id: String,
+	pub msg: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ReceiverExecuteMsg{
+	ReceiveNft(Cw721ReceiveMsg),
+}
+
+impl Cw721ReceiveMsg {
+	pub fn into_cosmos_msg(self, contract: String) -> StdResult<CosmosMsg> {
+		Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+			contract_addr: contract,
+			msg: to_binary(&ReceiverExecuteMsg::ReceiveNft(self))?,
+			funds: Vec::new(),
+		}))
+	}
+}
+
+//-------------Claim grant---------------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimGrant{
+	pub grantee: Addr, //address allowed to claim for the beneficiary
+	pub allow_recipient: bool,
+}
+
+//-------------Milestone-----------------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MilestoneStatus{
+	Pending,
+	Approved,
+	Rejected,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Milestone{
+	pub name: String,
+	#[serde(default)]
+	pub stage: Uint128, //only allocations of this stage are held back, the other stages vote
+	pub percent: Uint128, //share of every allocation of the stage held back until the milestone is approved
+	pub period: Uint128, //release the share linearly over this period after approval, 0 for at once
+	pub status: MilestoneStatus,
+	pub approved_at: Uint128, //block time of the approval
+	#[serde(default)]
+	pub vote: Option<MilestoneVote>, //investor vote deciding the milestone
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingConfig{
+	pub quorum: Uint128, //percent of the total allocation that has to vote
+	pub threshold: Uint128, //percent of the cast votes that has to approve
+	pub voting_period: Uint128, //seconds a vote stays open
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MilestoneVote{
+	pub end_time: Uint128,
+	pub yes: Uint128,
+	pub no: Uint128,
+	#[serde(default)]
+	pub total_power: Uint128, //backer allocation when the vote opened
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MilestoneTally{
+	pub name: String,
+	pub status: MilestoneStatus,
+	pub vote: Option<MilestoneVote>,
+	pub total_power: Uint128,
+	pub quorum_reached: bool,
+	pub threshold_reached: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StageStats{
+	pub total_amount: Uint128,
+	pub released_amount: Uint128,
+	pub obligation: Uint128, //tokens the contract still has to pay out
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProjectStats{
+	pub stages: Vec<StageStats>,
+	pub total_amount: Uint128,
+	pub released_amount: Uint128,
+	pub obligation: Uint128,
+}
+
+//------------Sale---------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SaleConfig{
+	pub price: Uint128, //amount of denom paid per allocation unit
+	pub denom: String,
+	pub min_amount: Uint128, //smallest total allocation a wallet may buy
+	pub max_amount: Uint128, //largest total allocation a wallet may buy
+	pub start_time: Uint128,
+	pub end_time: Uint128,
+	pub whitelist: Option<Vec<Addr>>, //None lets anyone buy
+	#[serde(default)]
+	pub soft_cap: Option<Uint128>, //funds to raise by end_time, held by the contract until then
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Sale{
+	pub config: SaleConfig,
+	pub sold: Uint128, //allocation sold so far
+	pub raised: Uint128, //funds paid so far
+	#[serde(default)]
+	pub withdrawn: bool, //raised funds of a soft-capped sale went to the admin
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleStatus{
+	Open, //until end_time
+	Succeeded, //allocations vest, the admin may withdraw
+	Failed, //soft cap missed, contributors may refund
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserAllocation{
+	pub wallet: String,
+	pub amount: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -98,4 +716,10 @@ pub struct ProjectInfo{
 	pub config: Config,
 	pub vest_param: Vec<VestingParameter>,
 	pub users: Vec<Vec<UserInfo>>,
+	#[serde(default)]
+	pub milestones: Vec<Milestone>,
+	#[serde(default)]
+	pub milestone_voting: Option<VotingConfig>,
+	#[serde(default)]
+	pub reward_per_share: Vec<Decimal>, //forfeited tokens redistributed per allocated token, by stage
 }
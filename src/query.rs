@@ -1,20 +1,25 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, Env, StdResult,
+    to_binary, Binary, Deps, Env, StdResult, StdError,
     Uint128, QueryRequest, BankQuery,
     Coin, AllBalanceResponse,
 };
 
-use cw20::{ Cw20QueryMsg, BalanceResponse as Cw20BalanceResponse, TokenInfoResponse };
+use cw_storage_plus::Bound;
+use cw20::{ Cw20QueryMsg, BalanceResponse as Cw20BalanceResponse, TokenInfoResponse, AllowanceResponse, Expiration };
 
-use crate::msg::{QueryMsg, Config, ProjectInfo, UserInfo};
-use crate::state::{PROJECT_INFOS, OWNER};
-use crate::contract::{ calc_pending };
+use crate::error::ContractError;
+use crate::msg::{QueryMsg, Config, ProjectInfo, UserInfo, MilestoneTally, ClaimGrant, ProjectStats, ProjectStatus, SaleStatus, Operation, Proposal,
+    OwnerOfResponse, ApprovedForAllResponse, NumTokensResponse, ContractInfoResponse, NftInfoResponse,
+    AllNftInfoResponse, TokensResponse, NftApproval, PositionExtension, VotingPowerAtHeightResponse, TotalPowerAtHeightResponse};
+use crate::state::{PROJECT_INFOS, ESCROWS, OWNER, CLAIM_GRANTS, LAUNCHPAD, SALES, FROZEN_USERS, OPERATIONS, PROPOSALS,
+    POSITIONS, NEXT_TOKEN_ID, NFT_OPERATORS, DEFAULT_LOCKED_TOKEN, VOTING_POWER, TOTAL_VOTING_POWER};
+use crate::contract::{ calc_locked, query_token_decimals, to_token_amount, is_allowed, sale_status, calc_pending, check_milestone_vote, calc_stage_stats };
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    let res = match msg {
         QueryMsg::GetBalance{ project_id, wallet } => 
             to_binary(&query_balance(deps, _env, project_id, wallet)?),
             
@@ -31,26 +36,105 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_binary(&query_getallprojectinfo(deps)?),
 
         QueryMsg::GetOwner{ } => {
-            let owner = OWNER.load(deps.storage).unwrap();
+            let owner = OWNER.load(deps.storage)?;
             to_binary(&owner)
         }
 
         QueryMsg::GetUserInfo{ project_id, wallet } =>
             to_binary(&query_getuserinfo(deps, project_id, wallet)?),
-    }
+
+        QueryMsg::GetMilestoneTally{ project_id, name } =>
+            to_binary(&query_milestonetally(deps, project_id, name)?),
+
+        QueryMsg::GetClaimGrants{ project_id, wallet } =>
+            to_binary(&query_claimgrants(deps, project_id, wallet)?),
+
+        QueryMsg::GetProjectStats{ project_id } =>
+            to_binary(&query_projectstats(deps, project_id)?),
+
+        QueryMsg::GetProjectsByAdmin{ admin } =>
+            to_binary(&query_projectsbyadmin(deps, admin)?),
+
+        QueryMsg::GetLaunchpad{ } =>
+            to_binary(&LAUNCHPAD.may_load(deps.storage)?),
+
+        QueryMsg::GetSale{ project_id, stage } =>
+            to_binary(&SALES.may_load(deps.storage, (project_id.u128().into(), stage.u128().into()))?),
+
+        QueryMsg::GetSaleStatus{ project_id, stage } =>
+            to_binary(&query_salestatus(deps, _env, project_id, stage)?),
+
+        QueryMsg::IsAllowed{ project_id, wallet } =>
+            to_binary(&query_isallowed(deps, project_id, wallet)?),
+
+        QueryMsg::GetPendingOperations{ project_id } =>
+            to_binary(&query_pendingoperations(deps, project_id)?),
+
+        QueryMsg::GetProposals{ project_id } =>
+            to_binary(&query_proposals(deps, project_id)?),
+
+        QueryMsg::GetEscrow{ project_id } =>
+            to_binary(&ESCROWS.may_load(deps.storage, project_id.u128().into())?.unwrap_or_default()),
+
+        QueryMsg::Balance{ address, project_id } =>
+            to_binary(&query_lockedbalance(deps, &_env, address, project_id)?),
+
+        QueryMsg::TokenInfo{ project_id } =>
+            to_binary(&query_lockedtokeninfo(deps, &_env, project_id)?),
+
+        QueryMsg::Allowance{ project_id, .. } => {
+            load_locked_token(deps, project_id)?;
+            to_binary(&AllowanceResponse{ allowance: Uint128::zero(), expires: Expiration::Never{ } })
+        }
+
+        QueryMsg::VotingPowerAtHeight{ wallet, height, project_id } =>
+            to_binary(&query_votingpoweratheight(deps, &_env, wallet, height, project_id)?),
+
+        QueryMsg::TotalPowerAtHeight{ height, project_id } =>
+            to_binary(&query_totalpoweratheight(deps, &_env, height, project_id)?),
+
+        QueryMsg::OwnerOf{ token_id, include_expired } =>
+            to_binary(&query_ownerof(deps, &_env, token_id, include_expired.unwrap_or(false))?),
+
+        QueryMsg::ApprovedForAll{ owner, include_expired, start_after, limit } =>
+            to_binary(&query_approvedforall(deps, &_env, owner, include_expired.unwrap_or(false), start_after, limit)?),
+
+        QueryMsg::NumTokens{ } =>
+            to_binary(&NumTokensResponse{ count: NEXT_TOKEN_ID.may_load(deps.storage)?.unwrap_or_default() }),
+
+        QueryMsg::ContractInfo{ } =>
+            to_binary(&ContractInfoResponse{ name: String::from("Vesting positions"), symbol: String::from("VEST") }),
+
+        QueryMsg::NftInfo{ token_id } =>
+            to_binary(&query_nftinfo(deps, token_id)?),
+
+        QueryMsg::AllNftInfo{ token_id, include_expired } =>
+            to_binary(&AllNftInfoResponse{
+                access: query_ownerof(deps, &_env, token_id.clone(), include_expired.unwrap_or(false))?,
+                info: query_nftinfo(deps, token_id)?,
+            }),
+
+        QueryMsg::Tokens{ owner, start_after, limit } =>
+            to_binary(&query_tokens(deps, Some(owner), start_after, limit)?),
+
+        QueryMsg::AllTokens{ start_after, limit } =>
+            to_binary(&query_tokens(deps, None, start_after, limit)?),
+    };
+    Ok(res?)
 }
 fn query_pendingtokens(deps:Deps, _env:Env, project_id: Uint128, wallet: String) 
-    -> StdResult<Uint128> 
+    -> Result<Uint128, ContractError> 
 {
     let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
 
     let mut amount = Uint128::zero();
-    for i in 0..x.users.len()-1{
-        let index = x.users[i].iter().position(|x| x.wallet_address == wallet);
-        if index != None {
+    //tokenized allocations are claimed by the NFT owner
+    for i in 0..x.users.len(){
+        let index = x.users[i].iter().position(|x| x.wallet_address == wallet && x.token_id.is_none());
+        if let Some(index) = index {
             let pending_amount = calc_pending(
-                deps.storage, _env.clone(), project_id, x.users[i][index.unwrap()].clone(), i
-            );
+                deps.storage, _env.clone(), &x, x.users[i][index].clone(), i
+            )?;
             amount += pending_amount;
         }
     }
@@ -99,7 +183,9 @@ fn query_getallprojectinfo(deps: Deps) -> StdResult<Vec<ProjectInfo>>
 
     let mut all_project:Vec<ProjectInfo> = Vec::new();
     for x in all{
-        all_project.push(x.1);
+        if x.1.config.status != ProjectStatus::Delisted {
+            all_project.push(x.1);
+        }
     }
     Ok(all_project)
 }
@@ -112,16 +198,279 @@ fn query_getuserinfo(deps:Deps, project_id: Uint128, wallet: String)
         total_amount: Uint128::zero(),
         released_amount: Uint128::zero(),
         wallet_address: deps.api.addr_validate(&wallet).unwrap(),
-        pending_amount: Uint128::zero()
+        pending_amount: Uint128::zero(),
+        early_claimed: false,
+        reward_debt: Uint128::zero(),
+        reward_amount: Uint128::zero(),
+        frozen: false,
+        token_id: None,
     };
+    user_info.frozen = FROZEN_USERS.has(deps.storage, (project_id.u128().into(), &user_info.wallet_address));
 
-    for i in 0..x.users.len()-1{
-        let index = x.users[i].iter().position(|x| x.wallet_address == wallet);
-        if index != None {
-            user_info.total_amount += x.users[i][index.unwrap()].total_amount;
-            user_info.released_amount += x.users[i][index.unwrap()].released_amount;
+    for i in 0..x.users.len(){
+        let index = x.users[i].iter().position(|x| x.wallet_address == wallet && x.token_id.is_none());
+        if let Some(index) = index {
+            user_info.total_amount += x.users[i][index].total_amount;
+            user_info.released_amount += x.users[i][index].released_amount;
         }
     }
 
     Ok(user_info)
-}
\ No newline at end of file
+}
+
+fn query_milestonetally(deps:Deps, project_id: Uint128, name: String)
+    -> Result<MilestoneTally, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let milestone = x.milestones.iter().find(|m| m.name == name)
+        .ok_or_else(|| StdError::not_found("Milestone"))?;
+
+    let total_power = milestone.vote.as_ref().map(|vote| vote.total_power).unwrap_or_default();
+    let (quorum_reached, threshold_reached) = match (&x.milestone_voting, &milestone.vote) {
+        (Some(voting), Some(vote)) => check_milestone_vote(voting, vote)?,
+        _ => (false, false),
+    };
+
+    Ok(MilestoneTally{
+        name,
+        status: milestone.status.clone(),
+        vote: milestone.vote.clone(),
+        total_power,
+        quorum_reached,
+        threshold_reached,
+    })
+}
+
+fn query_claimgrants(deps:Deps, project_id: Uint128, wallet: String)
+    -> StdResult<Vec<ClaimGrant>>
+{
+    let wallet = deps.api.addr_validate(&wallet)?;
+    CLAIM_GRANTS.prefix((project_id.u128().into(), &wallet))
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, grant)| grant))
+        .collect()
+}
+
+fn query_projectstats(deps:Deps, project_id: Uint128) -> Result<ProjectStats, ContractError>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let mut stats = ProjectStats{
+        stages: Vec::new(),
+        total_amount: Uint128::zero(),
+        released_amount: Uint128::zero(),
+        obligation: Uint128::zero(),
+    };
+    for i in 0..x.users.len(){
+        let stage = calc_stage_stats(&x, i)?;
+        stats.total_amount += stage.total_amount;
+        stats.released_amount += stage.released_amount;
+        stats.obligation += stage.obligation;
+        stats.stages.push(stage);
+    }
+    Ok(stats)
+}
+
+fn query_projectsbyadmin(deps:Deps, admin: String) -> StdResult<Vec<Uint128>>
+{
+    let admin = deps.api.addr_validate(&admin)?;
+    let mut project_ids = Vec::new();
+    for item in PROJECT_INFOS.range(deps.storage, None, None, cosmwasm_std::Order::Ascending) {
+        let (_, x) = item?;
+        if x.config.owner == admin && x.config.status != ProjectStatus::Delisted {
+            project_ids.push(x.project_id);
+        }
+    }
+    Ok(project_ids)
+}
+
+fn query_salestatus(deps:Deps, _env:Env, project_id: Uint128, stage: Uint128) -> StdResult<SaleStatus>
+{
+    let sale = SALES.load(deps.storage, (project_id.u128().into(), stage.u128().into()))?;
+    Ok(sale_status(&sale, Uint128::new(_env.block.time.seconds() as u128)))
+}
+
+fn query_isallowed(deps:Deps, project_id: Uint128, wallet: String) -> StdResult<bool>
+{
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    let wallet = deps.api.addr_validate(&wallet)?;
+    is_allowed(deps.storage, &deps.querier, &x, &wallet)
+}
+
+fn query_pendingoperations(deps:Deps, project_id: Uint128) -> StdResult<Vec<Operation>>
+{
+    OPERATIONS.prefix(project_id.u128().into())
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, operation)| operation))
+        .collect()
+}
+
+fn query_proposals(deps:Deps, project_id: Uint128) -> StdResult<Vec<Proposal>>
+{
+    PROPOSALS.prefix(project_id.u128().into())
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, proposal)| proposal))
+        .collect()
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+fn query_ownerof(deps:Deps, _env:&Env, token_id: String, include_expired: bool) -> StdResult<OwnerOfResponse>
+{
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+    let approvals = position.approvals.into_iter()
+        .filter(|approval| include_expired || !approval.expires.is_expired(&_env.block))
+        .collect();
+    Ok(OwnerOfResponse{ owner: position.owner.to_string(), approvals })
+}
+
+fn query_approvedforall(deps:Deps, _env:&Env, owner: String, include_expired: bool,
+    start_after: Option<String>, limit: Option<u32>) -> StdResult<ApprovedForAllResponse>
+{
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let mut operators = Vec::new();
+    for item in NFT_OPERATORS.prefix(&owner).range(deps.storage, start, None, cosmwasm_std::Order::Ascending) {
+        let (operator, expires) = item?;
+        if include_expired || !expires.is_expired(&_env.block) {
+            operators.push(NftApproval{ spender: String::from_utf8(operator)?, expires });
+        }
+        if operators.len() >= limit {
+            break;
+        }
+    }
+    Ok(ApprovedForAllResponse{ operators })
+}
+
+fn query_nftinfo(deps:Deps, token_id: String) -> StdResult<NftInfoResponse>
+{
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+    let x = PROJECT_INFOS.load(deps.storage, position.project_id.u128().into())?;
+    let user = x.users.get(position.stage.u128() as usize)
+        .and_then(|users| users.iter().find(|user| user.token_id.as_ref() == Some(&token_id)))
+        .ok_or_else(|| StdError::not_found("UserInfo"))?;
+    Ok(NftInfoResponse{
+        token_uri: None,
+        extension: PositionExtension{
+            project_id: position.project_id,
+            stage: position.stage,
+            total_amount: user.total_amount,
+            released_amount: user.released_amount,
+        },
+    })
+}
+
+//None lists the positions of every owner
+fn query_tokens(deps:Deps, owner: Option<String>, start_after: Option<String>, limit: Option<u32>)
+    -> StdResult<TokensResponse>
+{
+    let owner = match owner {
+        Some(owner) => Some(deps.api.addr_validate(&owner)?),
+        None => None,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let mut tokens = Vec::new();
+    for item in POSITIONS.range(deps.storage, start, None, cosmwasm_std::Order::Ascending) {
+        let (token_id, position) = item?;
+        if owner.is_none() || owner.as_ref() == Some(&position.owner) {
+            tokens.push(String::from_utf8(token_id)?);
+        }
+        if tokens.len() >= limit {
+            break;
+        }
+    }
+    Ok(TokensResponse{ tokens })
+}
+
+//project a query without project_id is about
+fn default_locked_token(deps:Deps, project_id: Option<Uint128>) -> Result<Uint128, ContractError>
+{
+    match project_id {
+        Some(project_id) => Ok(project_id),
+        None => DEFAULT_LOCKED_TOKEN.may_load(deps.storage)?.ok_or(ContractError::NoDefaultLockedToken{ }),
+    }
+}
+
+//project of a cw20 query, the default one when none is named
+fn load_locked_token(deps:Deps, project_id: Option<Uint128>) -> Result<ProjectInfo, ContractError>
+{
+    let project_id = default_locked_token(deps, project_id)?;
+    let x = PROJECT_INFOS.load(deps.storage, project_id.u128().into())?;
+    if !x.config.locked_token {
+        return Err(ContractError::LockedTokenDisabled{ project_id });
+    }
+    Ok(x)
+}
+
+//locked balances are in base units of the vested token, tokenized allocations count for the NFT owner
+fn query_lockedbalance(deps:Deps, _env:&Env, address: String, project_id: Option<Uint128>)
+    -> Result<Cw20BalanceResponse, ContractError>
+{
+    let x = load_locked_token(deps, project_id)?;
+    let address = deps.api.addr_validate(&address)?;
+
+    let mut balance = Uint128::zero();
+    for (stage, users) in x.users.iter().enumerate() {
+        for user in users.iter() {
+            let holder = match &user.token_id {
+                Some(token_id) => POSITIONS.load(deps.storage, token_id)?.owner,
+                None => user.wallet_address.clone(),
+            };
+            if holder == address {
+                balance = balance.checked_add(calc_locked(deps.storage, _env, &x, user, stage)?)?;
+            }
+        }
+    }
+    Ok(Cw20BalanceResponse{ balance: to_token_amount(&deps.querier, &x.config, balance)? })
+}
+
+fn query_lockedtokeninfo(deps:Deps, _env:&Env, project_id: Option<Uint128>)
+    -> Result<TokenInfoResponse, ContractError>
+{
+    let x = load_locked_token(deps, project_id)?;
+    let mut total_supply = Uint128::zero();
+    for (stage, users) in x.users.iter().enumerate() {
+        for user in users.iter() {
+            total_supply = total_supply.checked_add(calc_locked(deps.storage, _env, &x, user, stage)?)?;
+        }
+    }
+    let decimals = match x.config.decimals {
+        Some(decimals) => decimals,
+        None => query_token_decimals(&deps.querier, &x.config.token_addr)?,
+    };
+    Ok(TokenInfoResponse{
+        name: format!("Locked project {}", x.project_id),
+        symbol: String::from("LOCKED"),
+        decimals,
+        total_supply: to_token_amount(&deps.querier, &x.config, total_supply)?,
+    })
+}
+
+//power at the start of the block, the current one when no height is given.
+//snapshots hold project units, answers are in token base units like the locked balance
+fn query_votingpoweratheight(deps:Deps, _env:&Env, wallet: String, height: Option<u64>, project_id: Option<Uint128>)
+    -> Result<VotingPowerAtHeightResponse, ContractError>
+{
+    let project_id = default_locked_token(deps, project_id)?;
+    let x = load_locked_token(deps, Some(project_id))?;
+    let wallet = deps.api.addr_validate(&wallet)?;
+    let height = height.unwrap_or(_env.block.height);
+    let power = VOTING_POWER
+        .may_load_at_height(deps.storage, (project_id.u128().into(), &wallet), height)?
+        .unwrap_or_default();
+    Ok(VotingPowerAtHeightResponse{ power: to_token_amount(&deps.querier, &x.config, power)?, height })
+}
+
+fn query_totalpoweratheight(deps:Deps, _env:&Env, height: Option<u64>, project_id: Option<Uint128>)
+    -> Result<TotalPowerAtHeightResponse, ContractError>
+{
+    let project_id = default_locked_token(deps, project_id)?;
+    let x = load_locked_token(deps, Some(project_id))?;
+    let height = height.unwrap_or(_env.block.height);
+    let power = TOTAL_VOTING_POWER
+        .may_load_at_height(deps.storage, project_id.u128().into(), height)?
+        .unwrap_or_default();
+    Ok(TotalPowerAtHeightResponse{ power: to_token_amount(&deps.querier, &x.config, power)?, height })
+}
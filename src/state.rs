@@ -1,9 +1,73 @@
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-use cosmwasm_std::{Addr, Uint128, Coin, StdResult, DepsMut};
-use cw_storage_plus::{Item, Map, U128Key};
-use crate::msg::{ProjectInfo};
+use cosmwasm_std::{Addr, Uint128};
+use cw_storage_plus::{Item, Map, SnapshotMap, Strategy, U128Key};
+use cw20::Expiration;
+use crate::msg::{ProjectInfo, ClaimGrant, LaunchpadConfig, Sale, Operation, Proposal, Position};
 
 pub const OWNER: Item<Addr> = Item::new("owner");
 
+//present while anyone may add a project
+pub const LAUNCHPAD: Item<LaunchpadConfig> = Item::new("launchpad");
+
 pub const PROJECT_INFOS:Map<U128Key, ProjectInfo> = Map::new("project_infos");
+
+//project_id -> cw20 base units deposited for the project and not paid out yet
+pub const ESCROWS:Map<U128Key, Uint128> = Map::new("escrows");
+
+//lowest id the next AddProject without an explicit id tries
+pub const NEXT_PROJECT_ID: Item<Uint128> = Item::new("next_project_id");
+
+//project behind cw20 and voting power queries that name none
+pub const DEFAULT_LOCKED_TOKEN: Item<Uint128> = Item::new("default_locked_token");
+
+//(project_id, holder) -> allocation not released yet, kept per block for governance
+pub const VOTING_POWER: SnapshotMap<(U128Key, &Addr), Uint128> = SnapshotMap::new(
+    "voting_power", "voting_power__checkpoints", "voting_power__changelog", Strategy::EveryBlock);
+
+//project_id -> sum of VOTING_POWER of the project
+pub const TOTAL_VOTING_POWER: SnapshotMap<U128Key, Uint128> = SnapshotMap::new(
+    "total_voting_power", "total_voting_power__checkpoints", "total_voting_power__changelog", Strategy::EveryBlock);
+
+//(project_id, beneficiary, grantee) -> grant
+pub const CLAIM_GRANTS:Map<(U128Key, &Addr, &Addr), ClaimGrant> = Map::new("claim_grants");
+
+//(project_id, stage) -> last wallet distributed to, survives users being removed between batches
+pub const DISTRIBUTE_CURSORS:Map<(U128Key, U128Key), Addr> = Map::new("distribute_wallet_cursors");
+
+//(project_id, milestone name, voter) -> voting power when the vote opened
+pub const MILESTONE_POWER:Map<(U128Key, &str, &Addr), Uint128> = Map::new("milestone_power");
+
+//(project_id, milestone name, voter) -> approve
+pub const MILESTONE_VOTES:Map<(U128Key, &str, &Addr), bool> = Map::new("milestone_votes");
+
+//(project_id, wallet) -> approved without asking the registry
+pub const ALLOWLIST:Map<(U128Key, &Addr), bool> = Map::new("allowlist");
+
+//(project_id, wallet) -> claims on hold, vesting keeps accruing
+pub const FROZEN_USERS:Map<(U128Key, &Addr), bool> = Map::new("frozen_users");
+
+//(project_id, stage) -> sale
+pub const SALES:Map<(U128Key, U128Key), Sale> = Map::new("sales");
+
+//(project_id, stage, buyer) -> allocation bought
+pub const PURCHASES:Map<(U128Key, U128Key, &Addr), Uint128> = Map::new("purchases");
+
+//(project_id, stage, buyer) -> funds paid
+pub const CONTRIBUTIONS:Map<(U128Key, U128Key, &Addr), Uint128> = Map::new("contributions");
+
+//(project_id, operation_id) -> operation waiting out the timelock
+pub const OPERATIONS:Map<(U128Key, U128Key), Operation> = Map::new("operations");
+
+pub const NEXT_OPERATION_ID: Item<Uint128> = Item::new("next_operation_id");
+
+//(project_id, proposal_id) -> committee proposal
+pub const PROPOSALS:Map<(U128Key, U128Key), Proposal> = Map::new("proposals");
+
+pub const NEXT_PROPOSAL_ID: Item<Uint128> = Item::new("next_proposal_id");
+
+//token_id -> position NFT, ids are handed out in sequence and never burned
+pub const POSITIONS:Map<&str, Position> = Map::new("positions");
+
+pub const NEXT_TOKEN_ID: Item<u64> = Item::new("next_token_id");
+
+//(owner, operator) -> operator may move every position of the owner until expiry
+pub const NFT_OPERATORS:Map<(&Addr, &Addr), Expiration> = Map::new("nft_operators");
@@ -1,17 +1,109 @@
-use super::*;
-use cosmwasm_std::{from_binary, Addr, CosmosMsg, WasmMsg,
-    BankQuery, BalanceResponse, AllBalanceResponse, Coin, Uint128};
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR, mock_dependencies};
+use cosmwasm_std::{from_binary, to_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, OwnedDeps, SubMsg, Uint128, WasmMsg};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 
-use crate::contract::{execute, instantiate};
+use crate::contract::{execute, instantiate, migrate};
 use crate::query::{query};
-use crate::msg::{QueryMsg, ExecuteMsg, InstantiateMsg, VestingParameter, Config, UserInfo, ProjectInfo};
+use crate::msg::{QueryMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, VestingParameter, ProjectInfo,
+    VotingConfig, Milestone, MilestoneTally, MilestoneStatus, EarlyUnlock, PenaltyDestination, UserInfo, ClaimGrant, UserAllocation, ProjectStats, Config, AmountUnits,
+    LaunchpadConfig, ProjectFee, ProjectStatus, SaleConfig, Sale, SaleStatus, AllowlistConfig, Operation,
+    Committee, Proposal, ReceiveMsg, OwnerOfResponse, TokensResponse, NftInfoResponse, VotingPowerAtHeightResponse, TotalPowerAtHeightResponse};
+use crate::ContractError;
+use crate::state::{PROJECT_INFOS, ESCROWS};
 
-// use crate::mock_querier::mock_dependencies;
-use cw20::Cw20ExecuteMsg;
+use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, BalanceResponse as Cw20BalanceResponse, TokenInfoResponse, AllowanceResponse};
 // use terraswap::asset::{Asset, AssetInfo};
 // use terraswap::pair::ExecuteMsg as TerraswapExecuteMsg;
 
+//the vesting contract holds `amount` WeFund tokens
+fn funded_dependencies(amount: u128) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("WeFund"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(amount))],
+    )]);
+    deps
+}
+
+fn now() -> Uint128 {
+    Uint128::new(mock_env().block.time.seconds() as u128)
+}
+
+//one stage that is fully unlocked as soon as the release starts
+fn unlocked_params() -> Vec<VestingParameter> {
+    vec![VestingParameter{
+        soon: Uint128::new(100),
+        after: Uint128::zero(),
+        period: Uint128::new(1),
+        early_unlock: None,
+        redistribute_revoked: false
+    }]
+}
+
+fn setup_project(mut deps: DepsMut, vesting_params: Vec<VestingParameter>, start_time: Uint128) {
+    let info = mock_info("admin", &[]);
+    let msg = InstantiateMsg{ admin: None };
+    instantiate(deps.branch(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::AddProject{
+        project_id: Some(Uint128::new(1)),
+        admin: String::from("admin"),
+        token_addr: String::from("WeFund"),
+        vesting_params,
+        start_time,
+        units: None
+    };
+    execute(deps.branch(), mock_env(), info, msg).unwrap();
+    deposit(deps, "WeFund", 1, 100_000);
+}
+
+//funds the escrow of a project through the cw20 hook of its token
+fn deposit(deps: DepsMut, token: &str, project_id: u128, amount: u128) {
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+        sender: String::from("admin"),
+        amount: Uint128::new(amount),
+        msg: to_binary(&ReceiveMsg::Deposit{ project_id: Uint128::new(project_id) }).unwrap()
+    });
+    execute(deps, mock_env(), mock_info(token, &[]), msg).unwrap();
+}
+
+fn add_user(deps: DepsMut, stage: u128, wallet: &str, amount: u128) {
+    let msg = ExecuteMsg::AddUser{
+        project_id: Uint128::new(1),
+        stage: Uint128::new(stage),
+        wallet: Addr::unchecked(wallet),
+        amount: Uint128::new(amount)
+    };
+    execute(deps, mock_env(), mock_info("admin", &[]), msg).unwrap();
+}
+
+fn start_release(deps: DepsMut) {
+    let msg = ExecuteMsg::StartRelease{
+        project_id: Uint128::new(1),
+        start_time: now() - Uint128::new(10)
+    };
+    execute(deps, mock_env(), mock_info("admin", &[]), msg).unwrap();
+}
+
+fn cw20_transfer(recipient: &str, amount: u128) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute{
+        contract_addr: String::from("WeFund"),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer{
+            recipient: String::from(recipient),
+            amount: Uint128::new(amount)
+        }).unwrap(),
+        funds: vec![]
+    })
+}
+
+fn pending(deps: Deps, wallet: &str) -> Uint128 {
+    let msg = QueryMsg::GetPendingTokens{
+        project_id: Uint128::new(1),
+        wallet: String::from(wallet)
+    };
+    from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+}
+
 #[test]
 fn workflow(){
     let mut deps = mock_dependencies(&[]);
@@ -25,11 +117,12 @@ fn workflow(){
 //add community member
 
     let msg = ExecuteMsg::AddProject{
-        project_id: Uint128::from(1u64),
+        project_id: Some(Uint128::from(1u64)),
         admin: String::from("admin"),
         token_addr: String::from("WeFund"),
-        vest_param: Vec::new(),
-        start_time: Uint128::from(1645771274u128)
+        vesting_params: Vec::new(),
+        start_time: Uint128::from(1645771274u128),
+        units: None
     };
     // let msg = ExecuteMsg::AddSeedUser{
     //     project_id: 
@@ -56,3 +149,1552 @@ fn workflow(){
     println!("Project Info {:?}", res );
 }
 
+#[test]
+fn milestone_release(){
+    let mut deps = mock_dependencies(&[]);
+    let info = mock_info("admin", &[]);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 1000);
+
+    let msg = ExecuteMsg::AddMilestone{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        name: String::from("mainnet"),
+        percent: Uint128::new(40),
+        period: Uint128::zero()
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::AddMilestone{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        name: String::from("audit"),
+        percent: Uint128::new(61),
+        period: Uint128::zero()
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidMilestonePercent{}));
+
+    start_release(deps.as_mut());
+
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(600));
+
+    let msg = ExecuteMsg::ApproveMilestone{
+        project_id: Uint128::new(1),
+        name: String::from("mainnet")
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(1000));
+
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::MilestoneAlreadyDecided{ .. }));
+}
+
+#[test]
+fn milestone_vote(){
+    let mut deps = mock_dependencies(&[]);
+    let info = mock_info("admin", &[]);
+    let mut params = unlocked_params();
+    params.extend(unlocked_params());
+    setup_project(deps.as_mut(), params, Uint128::zero());
+    add_user(deps.as_mut(), 0, "whale", 700);
+    add_user(deps.as_mut(), 0, "minnow", 300);
+    add_user(deps.as_mut(), 1, "team", 1000);
+
+    //the team tranche is stage 1, backers on stage 0 decide on it
+    let msg = ExecuteMsg::AddMilestone{
+        project_id: Uint128::new(1),
+        stage: Uint128::new(1),
+        name: String::from("beta"),
+        percent: Uint128::new(50),
+        period: Uint128::zero()
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::SetMilestoneVoting{
+        project_id: Uint128::new(1),
+        voting: Some(VotingConfig{
+            quorum: Uint128::new(50),
+            threshold: Uint128::new(60),
+            voting_period: Uint128::new(100)
+        })
+    };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    start_release(deps.as_mut());
+    assert_eq!(pending(deps.as_ref(), "whale"), Uint128::new(700));
+    assert_eq!(pending(deps.as_ref(), "team"), Uint128::new(500));
+
+    let msg = ExecuteMsg::ApproveMilestone{ project_id: Uint128::new(1), name: String::from("beta") };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::MilestoneVotingEnabled{}));
+
+    let msg = ExecuteMsg::StartMilestoneVote{ project_id: Uint128::new(1), name: String::from("beta") };
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    //allocations added after the vote opened carry no weight
+    add_user(deps.as_mut(), 0, "latecomer", 5000);
+
+    let vote = |approve| ExecuteMsg::VoteMilestone{
+        project_id: Uint128::new(1),
+        name: String::from("beta"),
+        approve
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("whale", &[]), vote(true)).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info("whale", &[]), vote(true)).unwrap_err();
+    assert!(matches!(err, ContractError::AlreadyVoted{ .. }));
+    execute(deps.as_mut(), mock_env(), mock_info("minnow", &[]), vote(false)).unwrap();
+    for voter in ["stranger", "latecomer", "team"] {
+        let err = execute(deps.as_mut(), mock_env(), mock_info(voter, &[]), vote(false)).unwrap_err();
+        assert!(matches!(err, ContractError::NoVotingPower{}));
+    }
+
+    let tally = ExecuteMsg::TallyMilestoneVote{ project_id: Uint128::new(1), name: String::from("beta") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), tally.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::MilestoneVoteOpen{ .. }));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), tally).unwrap();
+
+    let msg = QueryMsg::GetMilestoneTally{ project_id: Uint128::new(1), name: String::from("beta") };
+    let res: MilestoneTally = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(res.status, MilestoneStatus::Approved);
+    assert_eq!(res.total_power, Uint128::new(1000));
+    assert!(res.quorum_reached && res.threshold_reached);
+    let msg = QueryMsg::GetPendingTokens{ project_id: Uint128::new(1), wallet: String::from("team") };
+    let team: Uint128 = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(team, Uint128::new(1000));
+}
+
+#[test]
+fn early_claim_redistributes_penalty(){
+    let mut deps = funded_dependencies(10000);
+    let params = vec![VestingParameter{
+        soon: Uint128::zero(),
+        after: Uint128::zero(),
+        period: Uint128::new(1000),
+        early_unlock: Some(EarlyUnlock{
+            penalty: Uint128::new(50),
+            decay: false,
+            destination: PenaltyDestination::Redistribute
+        }),
+        redistribute_revoked: false
+    }];
+    setup_project(deps.as_mut(), params, Uint128::zero());
+    add_user(deps.as_mut(), 0, "early", 1000);
+    add_user(deps.as_mut(), 0, "stay", 1000);
+
+    let msg = ExecuteMsg::EarlyClaim{ project_id: Uint128::new(1) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("early", &[]), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::ReleaseNotStarted{}));
+    start_release(deps.as_mut());
+
+    //10 unlocked, half of the remaining 990 is kept back
+    let res = execute(deps.as_mut(), mock_env(), mock_info("early", &[]), msg.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("early", 505));
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("early", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NoPendingTokens{}));
+
+    //the penalty is claimable right away, late comers don't share in it
+    add_user(deps.as_mut(), 0, "late", 1000);
+    assert_eq!(pending(deps.as_ref(), "stay"), Uint128::new(505));
+    assert_eq!(pending(deps.as_ref(), "late"), Uint128::new(10));
+
+    let msg = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("stay", &[]), msg).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("stay", 505));
+    assert_eq!(pending(deps.as_ref(), "stay"), Uint128::zero());
+
+    let msg_info = QueryMsg::GetUserInfo{ project_id: Uint128::new(1), wallet: String::from("stay") };
+    let user: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg_info).unwrap()).unwrap();
+    assert_eq!(user.total_amount, Uint128::new(1000));
+    assert_eq!(user.released_amount, Uint128::new(10));
+
+    //the last one out has nobody to share with, the penalty is reported and stays in the escrow
+    let msg = ExecuteMsg::EarlyClaim{ project_id: Uint128::new(1) };
+    execute(deps.as_mut(), mock_env(), mock_info("stay", &[]), msg.clone()).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("late", &[]), msg).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "unshared" && attr.value == "495"));
+}
+
+#[test]
+fn revoked_allocations_are_redistributed(){
+    let mut deps = funded_dependencies(10000);
+    let mut params = unlocked_params();
+    params[0].redistribute_revoked = true;
+    setup_project(deps.as_mut(), params, Uint128::zero());
+    add_user(deps.as_mut(), 0, "alice", 100);
+    add_user(deps.as_mut(), 0, "bob", 100);
+    add_user(deps.as_mut(), 0, "carol", 200);
+
+    let msg = ExecuteMsg::RemoveUser{ project_id: Uint128::new(1), stage: Uint128::zero(), wallet: String::from("carol") };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    //alice doesn't share in the part it loses itself
+    let msg = ExecuteMsg::SetAllocation{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from("alice"),
+        amount: Uint128::new(50)
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    start_release(deps.as_mut());
+    assert_eq!(pending(deps.as_ref(), "alice"), Uint128::new(150));
+    assert_eq!(pending(deps.as_ref(), "bob"), Uint128::new(250));
+
+    //a clawback of a sharing stage pays nothing out
+    let clawback = ExecuteMsg::Clawback{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from("bob"),
+        recipient: None
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), clawback).unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(pending(deps.as_ref(), "alice"), Uint128::new(250));
+    assert_eq!(pending(deps.as_ref(), "bob"), Uint128::new(150));
+}
+
+#[test]
+fn treasury_validated_when_set(){
+    let mut deps = mock_dependencies(&[]);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+
+    let params = |address: &str| vec![VestingParameter{
+        soon: Uint128::zero(),
+        after: Uint128::zero(),
+        period: Uint128::new(1000),
+        early_unlock: Some(EarlyUnlock{
+            penalty: Uint128::new(50),
+            decay: false,
+            destination: PenaltyDestination::Treasury{ address: String::from(address) }
+        }),
+        redistribute_revoked: false
+    }];
+    let msg = ExecuteMsg::SetVestingParameters{ project_id: Uint128::new(1), params: params("x") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Std(_)));
+    let msg = ExecuteMsg::SetVestingParameters{ project_id: Uint128::new(1), params: params("treasury") };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+}
+
+#[test]
+fn claim_for_beneficiary(){
+    let mut deps = funded_dependencies(10000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 1000);
+    start_release(deps.as_mut());
+
+    let claim = |beneficiary: Option<&str>, recipient: Option<&str>| ExecuteMsg::ClaimPendingTokens{
+        project_id: Uint128::new(1),
+        beneficiary: beneficiary.map(String::from),
+        recipient: recipient.map(String::from)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), claim(Some("investor"), None)).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+
+    let msg = ExecuteMsg::GrantClaim{
+        project_id: Uint128::new(1),
+        grantee: String::from("operator"),
+        allow_recipient: false
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap();
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]),
+        claim(Some("investor"), Some("operator"))).unwrap_err();
+    assert!(matches!(err, ContractError::RecipientNotAllowed{}));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("operator", &[]), claim(Some("investor"), None)).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("investor", 1000));
+
+    add_user(deps.as_mut(), 0, "investor", 500);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim(None, Some("cold"))).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("cold", 500));
+
+    let msg = ExecuteMsg::RevokeClaimGrant{ project_id: Uint128::new(1), grantee: String::from("operator") };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap();
+    let msg = QueryMsg::GetClaimGrants{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let grants: Vec<ClaimGrant> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(grants.is_empty());
+}
+
+#[test]
+fn claim_and_send(){
+    let mut deps = funded_dependencies(10000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 1000);
+    start_release(deps.as_mut());
+
+    let msg = ExecuteMsg::SetSendTargets{
+        project_id: Uint128::new(1),
+        targets: Some(vec![String::from("staking")])
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let claim = |contract: &str| ExecuteMsg::ClaimAndSend{
+        project_id: Uint128::new(1),
+        contract: String::from(contract),
+        msg: to_binary("bond").unwrap(),
+        beneficiary: None
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim("dex")).unwrap_err();
+    assert!(matches!(err, ContractError::SendTargetNotAllowed{ .. }));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim("staking")).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Wasm(WasmMsg::Execute{
+        contract_addr: String::from("WeFund"),
+        msg: to_binary(&Cw20ExecuteMsg::Send{
+            contract: String::from("staking"),
+            amount: Uint128::new(1000),
+            msg: to_binary("bond").unwrap()
+        }).unwrap(),
+        funds: vec![]
+    }));
+}
+
+#[test]
+fn distribute_with_cursor(){
+    let mut deps = funded_dependencies(10000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "first", 100);
+    add_user(deps.as_mut(), 0, "second", 200);
+    add_user(deps.as_mut(), 0, "third", 300);
+    start_release(deps.as_mut());
+
+    let msg = ExecuteMsg::Distribute{ project_id: Uint128::new(1), stage: Uint128::zero(), limit: 2 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("first", &[]), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[1].msg, cw20_transfer("second", 200));
+    assert_eq!(pending(deps.as_ref(), "first"), Uint128::zero());
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg.clone()).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].msg, cw20_transfer("third", 300));
+    assert!(res.attributes.iter().any(|attr| attr.key == "done" && attr.value == "true"));
+
+    //starts over from the beginning, nobody has anything left
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    assert!(res.messages.is_empty());
+}
+
+#[test]
+fn distribute_cursor_survives_removal(){
+    let mut deps = funded_dependencies(10000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "frozen", 50);
+    add_user(deps.as_mut(), 0, "first", 100);
+    add_user(deps.as_mut(), 0, "second", 200);
+    start_release(deps.as_mut());
+    let msg = ExecuteMsg::FreezeUser{ project_id: Uint128::new(1), wallet: String::from("frozen") };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Distribute{ project_id: Uint128::new(1), stage: Uint128::zero(), limit: 2 };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg.clone()).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(cw20_transfer("first", 100))]);
+
+    //the list shifts down by one, the next batch still starts after "first"
+    let remove = ExecuteMsg::RemoveUser{ project_id: Uint128::new(1), stage: Uint128::zero(), wallet: String::from("frozen") };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), remove).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(cw20_transfer("second", 200))]);
+
+    let msg = ExecuteMsg::Distribute{ project_id: Uint128::new(1), stage: Uint128::new(3), limit: 2 };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStage{ .. }));
+}
+
+#[test]
+fn add_users_batch(){
+    let mut deps = funded_dependencies(10000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "existing", 100);
+    start_release(deps.as_mut());
+    let msg = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    execute(deps.as_mut(), mock_env(), mock_info("existing", &[]), msg).unwrap();
+
+    let allocation = |wallet: &str, amount: u128| UserAllocation{
+        wallet: String::from(wallet),
+        amount: Uint128::new(amount)
+    };
+    let msg = ExecuteMsg::AddUsers{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        users: vec![allocation("new", 50), allocation("new", 50)]
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::DuplicateWallet{ .. }));
+    let msg = ExecuteMsg::AddUsers{
+        project_id: Uint128::new(1),
+        stage: Uint128::new(3),
+        users: vec![allocation("new", 50)]
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStage{ stage } if stage == Uint128::new(3)));
+
+    let msg = ExecuteMsg::AddUsers{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        users: vec![allocation("existing", 100), allocation("new", 50)]
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "added" && attr.value == "1"));
+    assert!(res.attributes.iter().any(|attr| attr.key == "updated" && attr.value == "1"));
+
+    let msg = QueryMsg::GetUserInfo{ project_id: Uint128::new(1), wallet: String::from("existing") };
+    let user: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user.total_amount, Uint128::new(200));
+    assert_eq!(user.released_amount, Uint128::new(100));
+    assert_eq!(pending(deps.as_ref(), "existing"), Uint128::new(100));
+}
+
+#[test]
+fn reduce_and_remove_allocation(){
+    let mut deps = funded_dependencies(10000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "claimed", 1000);
+    add_user(deps.as_mut(), 0, "idle", 500);
+    start_release(deps.as_mut());
+    let msg = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    execute(deps.as_mut(), mock_env(), mock_info("claimed", &[]), msg).unwrap();
+
+    let set_allocation = |amount: u128| ExecuteMsg::SetAllocation{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from("claimed"),
+        amount: Uint128::new(amount)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_allocation(999)).unwrap_err();
+    assert!(matches!(err, ContractError::AllocationBelowReleased{ .. }));
+    let msg = ExecuteMsg::SetAllocation{
+        project_id: Uint128::new(1),
+        stage: Uint128::new(3),
+        wallet: String::from("claimed"),
+        amount: Uint128::new(1200)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStage{ stage } if stage == Uint128::new(3)));
+    let msg = ExecuteMsg::RemoveUser{ project_id: Uint128::new(1), stage: Uint128::new(3), wallet: String::from("idle") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStage{ stage } if stage == Uint128::new(3)));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_allocation(1200)).unwrap();
+
+    let remove = |wallet: &str| ExecuteMsg::RemoveUser{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from(wallet)
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), remove("idle")).unwrap();
+
+    let msg = QueryMsg::GetProjectStats{ project_id: Uint128::new(1) };
+    let stats: ProjectStats = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(stats.total_amount, Uint128::new(1200));
+    assert_eq!(stats.released_amount, Uint128::new(1000));
+    assert_eq!(stats.obligation, Uint128::new(200));
+
+    //what was claimed stays, the rest of the allocation is dropped
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), remove("claimed")).unwrap();
+    assert_eq!(pending(deps.as_ref(), "claimed"), Uint128::zero());
+    let msg = QueryMsg::GetProjectStats{ project_id: Uint128::new(1) };
+    let stats: ProjectStats = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(stats.total_amount, Uint128::new(1000));
+    assert_eq!(stats.obligation, Uint128::zero());
+}
+
+#[test]
+fn move_allocation(){
+    let mut deps = funded_dependencies(10000);
+    let sec_per_day = 60 * 60 * 24;
+    let params = vec![
+        unlocked_params()[0].clone(),
+        VestingParameter{
+            soon: Uint128::new(10),
+            after: Uint128::new(sec_per_day),
+            period: Uint128::new(sec_per_day * 30),
+            early_unlock: None,
+            redistribute_revoked: false
+        }
+    ];
+    setup_project(deps.as_mut(), params, Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 1000);
+    start_release(deps.as_mut());
+    let msg = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::MoveAllocation{
+        project_id: Uint128::new(1),
+        from_stage: Uint128::zero(),
+        to_stage: Uint128::new(1),
+        wallet: String::from("investor"),
+        amount: Uint128::new(400)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    //400 released tokens moved along, the seed stage unlocks less than that for now
+    let res: ProjectInfo = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    assert_eq!(res.users[0][0].total_amount, Uint128::new(600));
+    assert_eq!(res.users[0][0].released_amount, Uint128::new(600));
+    assert_eq!(res.users[1][0].total_amount, Uint128::new(400));
+    assert_eq!(res.users[1][0].released_amount, Uint128::new(400));
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::zero());
+
+    let bad_move = |from_stage: u128, to_stage: u128, amount: u128| ExecuteMsg::MoveAllocation{
+        project_id: Uint128::new(1),
+        from_stage: Uint128::new(from_stage),
+        to_stage: Uint128::new(to_stage),
+        wallet: String::from("investor"),
+        amount: Uint128::new(amount)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), bad_move(7, 1, 10)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStage{ stage } if stage == Uint128::new(7)));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), bad_move(0, 1, 0)).unwrap_err();
+    assert!(matches!(err, ContractError::ZeroAmount{}));
+}
+
+#[test]
+fn project_info_locked_after_release(){
+    let mut deps = funded_dependencies(1000);
+    let mut params = unlocked_params();
+    params[0].early_unlock = Some(EarlyUnlock{
+        penalty: Uint128::new(10),
+        decay: false,
+        destination: PenaltyDestination::Burn
+    });
+    setup_project(deps.as_mut(), params, Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+    start_release(deps.as_mut());
+
+    let load = |deps: Deps| -> ProjectInfo { from_binary(&query(deps, mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap() };
+    let set_info = |project_info: ProjectInfo| ExecuteMsg::SetProjectInfo{ project_id: Uint128::new(1), project_info };
+
+    let mut gated = load(deps.as_ref());
+    gated.milestones.push(Milestone{
+        name: String::from("relock"),
+        stage: Uint128::zero(),
+        percent: Uint128::new(100),
+        period: Uint128::zero(),
+        status: MilestoneStatus::Pending,
+        approved_at: Uint128::zero(),
+        vote: None
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_info(gated)).unwrap_err();
+    assert!(matches!(err, ContractError::ReleaseAlreadyStarted{}));
+
+    let mut harsher = load(deps.as_ref());
+    harsher.vest_param[0].early_unlock.as_mut().unwrap().penalty = Uint128::new(90);
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_info(harsher)).unwrap_err();
+    assert!(matches!(err, ContractError::PenaltyIncreased{}));
+
+    //allocations can't be rewritten through the project info
+    let mut wiped = load(deps.as_ref());
+    wiped.users = vec![Vec::new()];
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_info(wiped)).unwrap();
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(100));
+}
+
+#[test]
+fn stages_follow_vesting_parameters(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    let set_params = |count: usize| ExecuteMsg::SetVestingParameters{
+        project_id: Uint128::new(1),
+        params: vec![unlocked_params()[0].clone(); count]
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_params(2)).unwrap();
+    add_user(deps.as_mut(), 1, "investor", 100);
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_params(1)).unwrap_err();
+    assert!(matches!(err, ContractError::StageNotEmpty{ stage } if stage == Uint128::new(1)));
+
+    //the id in the body can't point the project at another project's escrow
+    let mut project_info: ProjectInfo = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    project_info.project_id = Uint128::new(2);
+    project_info.vest_param.push(unlocked_params()[0].clone());
+    let msg = ExecuteMsg::SetProjectInfo{ project_id: Uint128::new(1), project_info };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let project_info: ProjectInfo = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    assert_eq!(project_info.project_id, Uint128::new(1));
+    assert_eq!(project_info.users.len(), 3);
+    add_user(deps.as_mut(), 2, "investor", 50);
+}
+
+#[test]
+fn vesting_parameters_locked_after_release(){
+    let mut deps = mock_dependencies(&[]);
+    let params = VestingParameter{
+        soon: Uint128::new(10),
+        after: Uint128::new(100),
+        period: Uint128::new(1000),
+        early_unlock: None,
+        redistribute_revoked: false
+    };
+    setup_project(deps.as_mut(), vec![params.clone()], Uint128::zero());
+
+    let set_params = |params: Vec<VestingParameter>| ExecuteMsg::SetVestingParameters{
+        project_id: Uint128::new(1),
+        params
+    };
+    let mut longer = params.clone();
+    longer.period = Uint128::new(2000);
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_params(vec![longer.clone()])).unwrap();
+    start_release(deps.as_mut());
+
+    let mut longest = longer.clone();
+    longest.period = Uint128::new(3000);
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_params(vec![longest])).unwrap_err();
+    assert!(matches!(err, ContractError::PeriodExtended{}));
+
+    let mut later = longer.clone();
+    later.after = Uint128::new(200);
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_params(vec![later])).unwrap_err();
+    assert!(matches!(err, ContractError::CliffExtended{}));
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]),
+        set_params(vec![longer.clone(), longer.clone()])).unwrap_err();
+    assert!(matches!(err, ContractError::StageCountChanged{}));
+
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_params(vec![params])).unwrap();
+
+    let msg = ExecuteMsg::StartRelease{ project_id: Uint128::new(1), start_time: now() + Uint128::new(10) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::StartTimeDelayed{}));
+}
+
+#[test]
+fn pending_errors_instead_of_panicking(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 1000);
+    //a zero period is rejected on entry, write it directly as a project stored before validation would
+    let mut x = PROJECT_INFOS.load(&deps.storage, 1u128.into()).unwrap();
+    x.vest_param[0].period = Uint128::zero();
+    PROJECT_INFOS.save(&mut deps.storage, 1u128.into(), &x).unwrap();
+
+    let msg = QueryMsg::GetPendingTokens{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let err = query(deps.as_ref(), mock_env(), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::ReleaseNotStarted{}));
+
+    start_release(deps.as_mut());
+    let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidVestingParameter{}));
+
+    let msg = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidVestingParameter{}));
+}
+
+#[test]
+fn add_project_validation(){
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_decimals("Decimals", 6);
+    let info = mock_info("admin", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), InstantiateMsg{ admin: None }).unwrap();
+
+    let add_project = |token_addr: &str, vesting_params: Vec<VestingParameter>| ExecuteMsg::AddProject{
+        project_id: Some(Uint128::new(1)),
+        admin: String::from("admin"),
+        token_addr: String::from(token_addr),
+        vesting_params,
+        start_time: Uint128::zero(),
+        units: None
+    };
+
+    let mut params = unlocked_params();
+    params[0].soon = Uint128::new(101);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), add_project("Decimals", params)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidVestingParameter{}));
+
+    let mut params = unlocked_params();
+    params[0].period = Uint128::zero();
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), add_project("Decimals", params)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidVestingParameter{}));
+
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), add_project("NotAToken", unlocked_params())).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidToken{ .. }));
+
+    execute(deps.as_mut(), mock_env(), info.clone(), add_project("Decimals", unlocked_params())).unwrap();
+    let msg = QueryMsg::GetConfig{ project_id: Uint128::new(1) };
+    let config: Config = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(config.decimals, Some(6));
+
+    let msg = ExecuteMsg::SetVestingParameters{ project_id: Uint128::new(1), params: Vec::new() };
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidVestingParameter{}));
+}
+
+#[test]
+fn amount_units(){
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_decimals("Decimals", 6);
+    deps.querier.with_token_balances(&[(
+        &String::from("Decimals"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(2_000_000_000))],
+    )]);
+    let info = mock_info("admin", &[]);
+    instantiate(deps.as_mut(), mock_env(), info.clone(), InstantiateMsg{ admin: None }).unwrap();
+
+    for (project_id, units) in [(1u128, Some(AmountUnits::Raw)), (2u128, None)] {
+        let msg = ExecuteMsg::AddProject{
+            project_id: Some(Uint128::new(project_id)),
+            admin: String::from("admin"),
+            token_addr: String::from("Decimals"),
+            vesting_params: unlocked_params(),
+            start_time: now() - Uint128::new(10),
+            units
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        deposit(deps.as_mut(), "Decimals", project_id, 1_500_000_000);
+        let msg = ExecuteMsg::AddUser{
+            project_id: Uint128::new(project_id),
+            stage: Uint128::zero(),
+            wallet: Addr::unchecked("investor"),
+            amount: Uint128::new(1500)
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    }
+
+    //pending is reported in the project units
+    let msg = QueryMsg::GetPendingTokens{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let raw: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(raw, Uint128::new(1500));
+
+    //voting power is reported in token base units, like the locked balance
+    let msg = ExecuteMsg::SetLockedToken{ project_id: Uint128::new(2), enabled: true };
+    execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let msg = QueryMsg::VotingPowerAtHeight{ wallet: String::from("investor"), height: None, project_id: Some(Uint128::new(2)) };
+    let mut env = mock_env();
+    env.block.height += 1;
+    let res: VotingPowerAtHeightResponse = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(res.power, Uint128::new(1_500_000_000));
+
+    let claim = |project_id: u128| ExecuteMsg::ClaimPendingTokens{
+        project_id: Uint128::new(project_id),
+        beneficiary: None,
+        recipient: None
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim(1)).unwrap();
+    assert_eq!(res.messages[0].msg, decimals_transfer("investor", 1500));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim(2)).unwrap();
+    assert_eq!(res.messages[0].msg, decimals_transfer("investor", 1_500_000_000));
+}
+
+fn decimals_transfer(recipient: &str, amount: u128) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute{
+        contract_addr: String::from("Decimals"),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer{
+            recipient: String::from(recipient),
+            amount: Uint128::new(amount)
+        }).unwrap(),
+        funds: vec![]
+    })
+}
+
+#[test]
+fn sequential_project_ids(){
+    let mut deps = mock_dependencies(&[]);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+
+    let add_project = |project_id: Option<Uint128>, admin: &str| ExecuteMsg::AddProject{
+        project_id,
+        admin: String::from(admin),
+        token_addr: String::from("WeFund"),
+        vesting_params: unlocked_params(),
+        start_time: Uint128::zero(),
+        units: None
+    };
+    let info = mock_info("admin", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), add_project(Some(Uint128::new(1)), "other")).unwrap_err();
+    assert!(matches!(err, ContractError::ProjectAlreadyExists{ .. }));
+
+    //the explicitly created project 1 is skipped
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), add_project(None, "other")).unwrap();
+    let project_id: Uint128 = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(project_id, Uint128::new(2));
+    assert!(res.attributes.iter().any(|a| a.key == "project_id" && a.value == "2"));
+
+    let res = execute(deps.as_mut(), mock_env(), info, add_project(None, "admin")).unwrap();
+    let project_id: Uint128 = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(project_id, Uint128::new(3));
+
+    let msg = QueryMsg::GetProjectsByAdmin{ admin: String::from("admin") };
+    let project_ids: Vec<Uint128> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(project_ids, vec![Uint128::new(1), Uint128::new(3)]);
+}
+
+#[test]
+fn launchpad_projects(){
+    let mut deps = mock_dependencies(&[]);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+
+    let add_project = ExecuteMsg::AddProject{
+        project_id: None,
+        admin: String::from("someone_else"),
+        token_addr: String::from("WeFund"),
+        vesting_params: unlocked_params(),
+        start_time: Uint128::zero(),
+        units: None
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), add_project.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+
+    let msg = ExecuteMsg::SetLaunchpad{ launchpad: Some(LaunchpadConfig{
+        fee: Some(ProjectFee::Native{ denom: String::from("uusd"), amount: Uint128::new(100) }),
+        treasury: String::from("treasury")
+    })};
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[Coin::new(99, "uusd")]), add_project.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::FeeNotPaid{}));
+
+    let funds = [Coin::new(100, "uusd"), Coin::new(5, "uluna")];
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &funds), add_project.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::UnexpectedFunds{ .. }));
+
+    //the surplus goes back to the creator
+    let res = execute(deps.as_mut(), mock_env(), mock_info("creator", &[Coin::new(150, "uusd")]), add_project).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send{
+        to_address: String::from("treasury"),
+        amount: vec![Coin::new(100, "uusd")]
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send{
+        to_address: String::from("creator"),
+        amount: vec![Coin::new(50, "uusd")]
+    }));
+    let msg = QueryMsg::GetProjectsByAdmin{ admin: String::from("creator") };
+    let project_ids: Vec<Uint128> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(project_ids, vec![Uint128::new(2)]);
+
+    //sharing the token of project 1 gives no access to its escrow
+    let add_user = ExecuteMsg::AddUser{
+        project_id: Uint128::new(2),
+        stage: Uint128::zero(),
+        wallet: Addr::unchecked("creator"),
+        amount: Uint128::new(1000)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), add_user.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::NotEnoughBalance{}));
+    let deposit_msg = ExecuteMsg::Receive(Cw20ReceiveMsg{
+        sender: String::from("creator"),
+        amount: Uint128::new(1000),
+        msg: to_binary(&ReceiveMsg::Deposit{ project_id: Uint128::new(2) }).unwrap()
+    });
+    let err = execute(deps.as_mut(), mock_env(), mock_info("FakeToken", &[]), deposit_msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidToken{ .. }));
+    deposit(deps.as_mut(), "WeFund", 2, 1000);
+    execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), add_user).unwrap();
+    let msg = QueryMsg::GetEscrow{ project_id: Uint128::new(1) };
+    let escrow: Uint128 = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(escrow, Uint128::new(100_000));
+
+    //a paused project only listens to the owner
+    let pause = |status: ProjectStatus| ExecuteMsg::SetProjectStatus{ project_id: Uint128::new(2), status };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), pause(ProjectStatus::Active)).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), pause(ProjectStatus::Paused)).unwrap();
+    let msg = ExecuteMsg::AddUser{
+        project_id: Uint128::new(2),
+        stage: Uint128::zero(),
+        wallet: Addr::unchecked("investor"),
+        amount: Uint128::new(100)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::ProjectPaused{ .. }));
+
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), pause(ProjectStatus::Delisted)).unwrap();
+    let msg = QueryMsg::GetAllProjectInfo{ };
+    let all: Vec<ProjectInfo> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(all.len(), 1);
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("creator", &[]),
+        ExecuteMsg::SetConfig{ admin: String::from("creator") }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+}
+
+#[test]
+fn stage_sale_purchase(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+
+    let sale = SaleConfig{
+        price: Uint128::new(10),
+        denom: String::from("uusd"),
+        min_amount: Uint128::new(5),
+        max_amount: Uint128::new(50),
+        start_time: now() - Uint128::new(100),
+        end_time: now() + Uint128::new(100),
+        whitelist: Some(vec![Addr::unchecked("x")]),
+        soft_cap: None
+    };
+    let msg = ExecuteMsg::SetSale{ project_id: Uint128::new(1), stage: Uint128::zero(), sale: Some(sale.clone()) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::Std(_)));
+    let sale = SaleConfig{ whitelist: Some(vec![Addr::unchecked("investor")]), ..sale };
+    let msg = ExecuteMsg::SetSale{ project_id: Uint128::new(1), stage: Uint128::zero(), sale: Some(sale) };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let purchase = ExecuteMsg::Purchase{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("outsider", &[Coin::new(100, "uusd")]), purchase.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::NotWhitelisted{}));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[Coin::new(40, "uusd")]), purchase.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::PurchaseOutOfRange{}));
+    let funds = [Coin::new(305, "uusd"), Coin::new(999, "uluna")];
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &funds), purchase.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::UnexpectedFunds{ .. }));
+
+    //the part of the funds that doesn't buy a whole unit is returned
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[Coin::new(305, "uusd")]), purchase.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send{
+        to_address: String::from("admin"),
+        amount: vec![Coin::new(300, "uusd")]
+    }));
+    assert_eq!(res.messages[1].msg, CosmosMsg::Bank(BankMsg::Send{
+        to_address: String::from("investor"),
+        amount: vec![Coin::new(5, "uusd")]
+    }));
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[Coin::new(300, "uusd")]), purchase).unwrap_err();
+    assert!(matches!(err, ContractError::PurchaseOutOfRange{}));
+
+    let msg = QueryMsg::GetSale{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let sale: Option<Sale> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(sale.clone().unwrap().sold, Uint128::new(30));
+
+    //funds already went to the admin, a soft cap can't make them refundable again
+    let capped = SaleConfig{ soft_cap: Some(Uint128::new(100_000)), end_time: now() - Uint128::new(1), ..sale.unwrap().config };
+    let msg = ExecuteMsg::SetSale{ project_id: Uint128::new(1), stage: Uint128::zero(), sale: Some(capped.clone()) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidSale{}));
+    let remove = ExecuteMsg::SetSale{ project_id: Uint128::new(1), stage: Uint128::zero(), sale: None };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), remove).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let refund = ExecuteMsg::Refund{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), refund).unwrap_err();
+    assert!(matches!(err, ContractError::NothingToWithdraw{}));
+
+    start_release(deps.as_mut());
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(30));
+}
+
+#[test]
+fn soft_cap_refund_and_withdraw(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "team", 100);
+    start_release(deps.as_mut());
+
+    let set_sale = |soft_cap: u128| ExecuteMsg::SetSale{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        sale: Some(SaleConfig{
+            price: Uint128::new(1),
+            denom: String::from("uusd"),
+            min_amount: Uint128::zero(),
+            max_amount: Uint128::new(1000),
+            start_time: now() - Uint128::new(100),
+            end_time: now() + Uint128::new(100),
+            whitelist: None,
+            soft_cap: Some(Uint128::new(soft_cap))
+        })
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_sale(500)).unwrap();
+
+    let purchase = ExecuteMsg::Purchase{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[Coin::new(300, "uusd")]), purchase).unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::zero());
+
+    //the terms are fixed once funds came in
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_sale(100)).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidSale{}));
+
+    let refund = ExecuteMsg::Refund{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), refund.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::SaleNotFailed{ .. }));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let msg = QueryMsg::GetSaleStatus{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let status: SaleStatus = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(status, SaleStatus::Failed);
+    //only the bought part waits for the sale, other allocations of the stage vest
+    let msg = QueryMsg::GetPendingTokens{ project_id: Uint128::new(1), wallet: String::from("team") };
+    let team: Uint128 = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(team, Uint128::new(100));
+    let msg = QueryMsg::GetPendingTokens{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let investor: Uint128 = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(investor, Uint128::zero());
+
+    let withdraw = ExecuteMsg::WithdrawRaised{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), withdraw).unwrap_err();
+    assert!(matches!(err, ContractError::SaleNotSucceeded{ .. }));
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("investor", &[]), refund.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send{
+        to_address: String::from("investor"),
+        amount: vec![Coin::new(300, "uusd")]
+    }));
+    let err = execute(deps.as_mut(), env, mock_info("investor", &[]), refund).unwrap_err();
+    assert!(matches!(err, ContractError::NothingToWithdraw{}));
+    let stats: ProjectStats = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectStats{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    assert_eq!(stats.total_amount, Uint128::new(100));
+}
+
+#[test]
+fn soft_cap_reached(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    start_release(deps.as_mut());
+
+    let msg = ExecuteMsg::SetSale{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        sale: Some(SaleConfig{
+            price: Uint128::new(1),
+            denom: String::from("uusd"),
+            min_amount: Uint128::zero(),
+            max_amount: Uint128::new(1000),
+            start_time: now() - Uint128::new(100),
+            end_time: now() + Uint128::new(100),
+            whitelist: None,
+            soft_cap: Some(Uint128::new(500))
+        })
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let purchase = ExecuteMsg::Purchase{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[Coin::new(600, "uusd")]), purchase).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let withdraw = ExecuteMsg::WithdrawRaised{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), withdraw.clone()).unwrap();
+    assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send{
+        to_address: String::from("admin"),
+        amount: vec![Coin::new(600, "uusd")]
+    }));
+    let err = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), withdraw).unwrap_err();
+    assert!(matches!(err, ContractError::NothingToWithdraw{}));
+
+    let msg = QueryMsg::GetPendingTokens{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let pending: Uint128 = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(pending, Uint128::new(600));
+}
+
+#[test]
+fn allowlist_gating(){
+    let mut deps = funded_dependencies(1000);
+    deps.querier.with_registry("registry", &["kyced"]);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+
+    let msg = ExecuteMsg::SetAllowlist{
+        project_id: Uint128::new(1),
+        allowlist: Some(AllowlistConfig{ registry: Some(String::from("registry")) })
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::AddUser{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: Addr::unchecked("stranger"),
+        amount: Uint128::new(100)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::WalletNotAllowed{ .. }));
+    add_user(deps.as_mut(), 0, "kyced", 100);
+
+    start_release(deps.as_mut());
+    let claim = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::WalletNotAllowed{ .. }));
+    execute(deps.as_mut(), mock_env(), mock_info("kyced", &[]), claim.clone()).unwrap();
+
+    //the local allowlist lets a wallet in without the registry
+    let msg = ExecuteMsg::UpdateAllowlist{
+        project_id: Uint128::new(1),
+        add: vec![String::from("investor")],
+        remove: vec![]
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = QueryMsg::IsAllowed{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let allowed: bool = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(allowed);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("investor", 100));
+}
+
+#[test]
+fn frozen_user_keeps_vesting(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+    add_user(deps.as_mut(), 0, "other", 100);
+
+    let freeze = ExecuteMsg::FreezeUser{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), freeze.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), freeze).unwrap();
+    start_release(deps.as_mut());
+
+    let msg = QueryMsg::GetUserInfo{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let user: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(user.frozen);
+
+    let claim = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::UserFrozen{ .. }));
+
+    //distribution passes over the frozen user
+    let msg = ExecuteMsg::Distribute{ project_id: Uint128::new(1), stage: Uint128::zero(), limit: 10 };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].msg, cw20_transfer("other", 100));
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(100));
+
+    let unfreeze = ExecuteMsg::UnfreezeUser{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), unfreeze).unwrap();
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("investor", 100));
+}
+
+#[test]
+fn timelocked_operations(){
+    let mut deps = funded_dependencies(1000);
+    let params = VestingParameter{
+        soon: Uint128::zero(),
+        after: Uint128::zero(),
+        period: Uint128::new(100),
+        early_unlock: None,
+        redistribute_revoked: false
+    };
+    setup_project(deps.as_mut(), vec![params], Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+    start_release(deps.as_mut());
+
+    let msg = ExecuteMsg::SetTimelock{ project_id: Uint128::new(1), delay: Uint128::new(1000) };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let clawback = ExecuteMsg::Clawback{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from("investor"),
+        recipient: None
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), clawback.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::OperationTimelocked{}));
+    let shorter = ExecuteMsg::SetTimelock{ project_id: Uint128::new(1), delay: Uint128::zero() };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), shorter).unwrap_err();
+    assert!(matches!(err, ContractError::OperationTimelocked{}));
+
+    let queue = |msg: ExecuteMsg| ExecuteMsg::QueueOperation{ project_id: Uint128::new(1), msg: Box::new(msg) };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), queue(clawback.clone())).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), queue(clawback)).unwrap();
+
+    let msg = QueryMsg::GetPendingOperations{ project_id: Uint128::new(1) };
+    let operations: Vec<Operation> = from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+    assert_eq!(operations.len(), 2);
+    assert_eq!(operations[0].eta, now() + Uint128::new(1000));
+
+    let run = |operation_id: u128| ExecuteMsg::ExecuteOperation{
+        project_id: Uint128::new(1),
+        operation_id: Uint128::new(operation_id)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), run(0)).unwrap_err();
+    assert!(matches!(err, ContractError::OperationNotReady{ .. }));
+
+    let cancel = ExecuteMsg::CancelOperation{ project_id: Uint128::new(1), operation_id: Uint128::new(1) };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), cancel).unwrap();
+
+    //the investor got the first 10% out before the clawback ran
+    let claim = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(1000);
+    let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), run(0)).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("admin", 90));
+    let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), run(1)).unwrap_err();
+    assert!(matches!(err, ContractError::OperationNotFound{ .. }));
+
+    let operations: Vec<Operation> = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert!(operations.is_empty());
+}
+
+#[test]
+fn timelock_covers_allocation_changes(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+    let msg = ExecuteMsg::SetTimelock{ project_id: Uint128::new(1), delay: Uint128::new(1000) };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let project_id = Uint128::new(1);
+    let wallet = String::from("investor");
+    let sensitive = vec![
+        ExecuteMsg::SetAllocation{ project_id, stage: Uint128::zero(), wallet: wallet.clone(), amount: Uint128::zero() },
+        ExecuteMsg::RemoveUser{ project_id, stage: Uint128::zero(), wallet: wallet.clone() },
+        ExecuteMsg::SetUsers{ project_id, stage: Uint128::zero(), user_infos: vec![] },
+        ExecuteMsg::MoveAllocation{ project_id, from_stage: Uint128::zero(), to_stage: Uint128::new(1),
+            wallet: wallet.clone(), amount: Uint128::new(100) },
+        ExecuteMsg::SetAllowlist{ project_id, allowlist: Some(AllowlistConfig{ registry: None }) },
+        ExecuteMsg::UpdateAllowlist{ project_id, add: vec![], remove: vec![wallet] },
+        ExecuteMsg::SetSendTargets{ project_id, targets: Some(vec![]) },
+        ExecuteMsg::SetCommittee{ project_id, committee: Some(Committee{
+            members: vec![Addr::unchecked("alice")],
+            threshold: 1,
+            expiry: Uint128::new(100)
+        }) },
+    ];
+    for msg in sensitive {
+        let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::OperationTimelocked{}), "{:?}", msg);
+        let queue = ExecuteMsg::QueueOperation{ project_id, msg: Box::new(msg) };
+        execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), queue).unwrap();
+    }
+    //nothing took effect right away
+    let msg = QueryMsg::GetUserInfo{ project_id, wallet: String::from("investor") };
+    let user: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user.total_amount, Uint128::new(100));
+}
+
+#[test]
+fn committee_proposals(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+
+    let set_committee = |members: &[&str]| ExecuteMsg::SetCommittee{
+        project_id: Uint128::new(1),
+        committee: Some(Committee{
+            members: members.iter().map(|member| Addr::unchecked(*member)).collect(),
+            threshold: 2,
+            expiry: Uint128::new(100)
+        })
+    };
+    //the project info can't slip in an unchecked committee
+    let mut project_info: ProjectInfo = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    project_info.config.committee = Some(Committee{ members: Vec::new(), threshold: 0, expiry: Uint128::new(100) });
+    project_info.config.timelock = Uint128::new(1000);
+    let msg = ExecuteMsg::SetProjectInfo{ project_id: Uint128::new(1), project_info };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let project_info: ProjectInfo = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    assert_eq!(project_info.config.committee, None);
+    assert_eq!(project_info.config.timelock, Uint128::zero());
+
+    //a doubled member can't reach the threshold alone
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_committee(&["alice", "alice"])).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidCommittee{}));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_committee(&["alice", "x"])).unwrap_err();
+    assert!(matches!(err, ContractError::Std(_)));
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_committee(&["alice", "bob", "carol", "bob"])).unwrap();
+
+    //the project admin is the contract now, single keys can't act for it
+    let add_user_msg = ExecuteMsg::AddUser{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: Addr::unchecked("investor"),
+        amount: Uint128::new(100)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), add_user_msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+
+    let propose = ExecuteMsg::Propose{ project_id: Uint128::new(1), msg: Box::new(add_user_msg) };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("mallory", &[]), propose.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::NotCommitteeMember{}));
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), propose.clone()).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), propose).unwrap();
+
+    let run = |proposal_id: u128| ExecuteMsg::ExecuteProposal{
+        project_id: Uint128::new(1),
+        proposal_id: Uint128::new(proposal_id)
+    };
+    let approve = |proposal_id: u128| ExecuteMsg::ApproveProposal{
+        project_id: Uint128::new(1),
+        proposal_id: Uint128::new(proposal_id)
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), run(0)).unwrap_err();
+    assert!(matches!(err, ContractError::ThresholdNotReached{ .. }));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), approve(0)).unwrap_err();
+    assert!(matches!(err, ContractError::AlreadyApproved{ .. }));
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), approve(0)).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), run(0)).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), run(0)).unwrap_err();
+    assert!(matches!(err, ContractError::ProposalClosed{ .. }));
+
+    //the second proposal runs out of time
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let err = execute(deps.as_mut(), env, mock_info("bob", &[]), approve(1)).unwrap_err();
+    assert!(matches!(err, ContractError::ProposalClosed{ .. }));
+
+    let msg = QueryMsg::GetProposals{ project_id: Uint128::new(1) };
+    let proposals: Vec<Proposal> = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(proposals[0].executed);
+    start_release(deps.as_mut());
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(100));
+}
+
+#[test]
+fn position_nft_claims(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+
+    let msg = ExecuteMsg::Tokenize{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg.clone()).unwrap();
+    assert!(res.attributes.iter().any(|a| a.key == "token_id" && a.value == "0"));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::PositionTokenized{ .. }));
+
+    let transfer = ExecuteMsg::TransferNft{ recipient: String::from("buyer"), token_id: String::from("0") };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), transfer.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    let approve = ExecuteMsg::Approve{ spender: String::from("market"), token_id: String::from("0"), expires: None };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), approve).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("market", &[]), transfer).unwrap();
+
+    let msg = QueryMsg::OwnerOf{ token_id: String::from("0"), include_expired: None };
+    let owner: OwnerOfResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(owner.owner, "buyer");
+    assert!(owner.approvals.is_empty());
+    let msg = QueryMsg::Tokens{ owner: String::from("buyer"), start_after: None, limit: None };
+    let tokens: TokensResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(tokens.tokens, vec![String::from("0")]);
+
+    //the wallet the allocation is recorded under has nothing left to claim
+    start_release(deps.as_mut());
+    let claim = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim).unwrap_err();
+    assert!(matches!(err, ContractError::NoPendingTokens{}));
+
+    let claim = ExecuteMsg::ClaimPosition{ project_id: Uint128::new(1), token_id: String::from("0"), recipient: None };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), claim).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("buyer", 100));
+
+    let msg = QueryMsg::NftInfo{ token_id: String::from("0") };
+    let info: NftInfoResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(info.extension.released_amount, Uint128::new(100));
+}
+
+#[test]
+fn locked_token_balances(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+
+    let balance = QueryMsg::Balance{ address: String::from("investor"), project_id: Some(Uint128::new(1)) };
+    let err = query(deps.as_ref(), mock_env(), balance.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::LockedTokenDisabled{ .. }));
+    let msg = QueryMsg::Balance{ address: String::from("investor"), project_id: None };
+    let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::NoDefaultLockedToken{ }));
+
+    let msg = ExecuteMsg::SetLockedToken{ project_id: Uint128::new(1), enabled: true };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = ExecuteMsg::SetDefaultLockedToken{ project_id: Some(Uint128::new(1)) };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+
+    let res: Cw20BalanceResponse = from_binary(&query(deps.as_ref(), mock_env(), balance.clone()).unwrap()).unwrap();
+    assert_eq!(res.balance, Uint128::new(100));
+    let msg = QueryMsg::TokenInfo{ project_id: None };
+    let res: TokenInfoResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.total_supply, Uint128::new(100));
+    assert_eq!(res.decimals, 0);
+
+    //query only, nothing moves or gets approved
+    let msgs = vec![
+        ExecuteMsg::Transfer{ recipient: String::from("other"), amount: Uint128::new(10) },
+        ExecuteMsg::Send{ contract: String::from("other"), amount: Uint128::new(10), msg: to_binary(&0).unwrap() },
+        ExecuteMsg::IncreaseAllowance{ spender: String::from("other"), amount: Uint128::new(10), expires: None },
+        ExecuteMsg::TransferFrom{ owner: String::from("investor"), recipient: String::from("other"), amount: Uint128::new(10) },
+    ];
+    for msg in msgs {
+        let err = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::LockedTokenNotTransferable{ }));
+    }
+    let msg = QueryMsg::Allowance{ owner: String::from("investor"), spender: String::from("other"), project_id: None };
+    let res: AllowanceResponse = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.allowance, Uint128::zero());
+    let res: Cw20BalanceResponse = from_binary(&query(deps.as_ref(), mock_env(), balance.clone()).unwrap()).unwrap();
+    assert_eq!(res.balance, Uint128::new(100));
+
+    //everything unlocks at release, nothing stays locked even before it is claimed
+    start_release(deps.as_mut());
+    let balance = QueryMsg::Balance{ address: String::from("investor"), project_id: None };
+    let res: Cw20BalanceResponse = from_binary(&query(deps.as_ref(), mock_env(), balance).unwrap()).unwrap();
+    assert_eq!(res.balance, Uint128::zero());
+}
+
+#[test]
+fn voting_power_snapshots(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 100);
+
+    //nothing is tracked until the locked token is on, and only SetLockedToken turns it on
+    let mut project_info: ProjectInfo = from_binary(&query(deps.as_ref(), mock_env(),
+        QueryMsg::GetProjectInfo{ project_id: Uint128::new(1) }).unwrap()).unwrap();
+    project_info.config.locked_token = true;
+    let msg = ExecuteMsg::SetProjectInfo{ project_id: Uint128::new(1), project_info };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let msg = QueryMsg::TotalPowerAtHeight{ height: None, project_id: Some(Uint128::new(1)) };
+    let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    assert!(matches!(err, ContractError::LockedTokenDisabled{ .. }));
+    let msg = ExecuteMsg::SetLockedToken{ project_id: Uint128::new(1), enabled: true };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    add_user(deps.as_mut(), 0, "other", 50);
+    start_release(deps.as_mut());
+
+    let mut env = mock_env();
+    let added = env.block.height;
+    env.block.height += 5;
+    let msg = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    execute(deps.as_mut(), env.clone(), mock_info("investor", &[]), msg).unwrap();
+
+    let power = |height: u64| -> (Uint128, Uint128) {
+        let msg = QueryMsg::VotingPowerAtHeight{ wallet: String::from("investor"), height: Some(height), project_id: Some(Uint128::new(1)) };
+        let wallet: VotingPowerAtHeightResponse = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        let msg = QueryMsg::TotalPowerAtHeight{ height: Some(height), project_id: Some(Uint128::new(1)) };
+        let total: TotalPowerAtHeightResponse = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        (wallet.power, total.power)
+    };
+    //a snapshot holds the power from the start of its block
+    assert_eq!(power(added), (Uint128::zero(), Uint128::zero()));
+    assert_eq!(power(added + 1), (Uint128::new(100), Uint128::new(150)));
+    assert_eq!(power(env.block.height), (Uint128::new(100), Uint128::new(150)));
+    assert_eq!(power(env.block.height + 1), (Uint128::zero(), Uint128::new(50)));
+
+    //a position carries its power to the new owner
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    let msg = ExecuteMsg::SetLockedToken{ project_id: Uint128::new(1), enabled: true };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    add_user(deps.as_mut(), 0, "investor", 100);
+    let msg = ExecuteMsg::Tokenize{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap();
+    let mut env = mock_env();
+    env.block.height += 1;
+    let transfer = ExecuteMsg::TransferNft{ recipient: String::from("buyer"), token_id: String::from("0") };
+    execute(deps.as_mut(), env.clone(), mock_info("investor", &[]), transfer).unwrap();
+
+    env.block.height += 1;
+    let power = |wallet: &str| -> Uint128 {
+        let msg = QueryMsg::VotingPowerAtHeight{ wallet: String::from(wallet), height: None, project_id: Some(Uint128::new(1)) };
+        let res: VotingPowerAtHeightResponse = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        res.power
+    };
+    assert_eq!(power("investor"), Uint128::zero());
+    assert_eq!(power("buyer"), Uint128::new(100));
+    let msg = QueryMsg::TotalPowerAtHeight{ height: None, project_id: Some(Uint128::new(1)) };
+    let res: TotalPowerAtHeightResponse = from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(res.power, Uint128::new(100));
+}
+
+#[test]
+fn tokenized_entry_is_not_topped_up(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    //a stray token id can't be imported onto a row
+    let stray = UserInfo{
+        wallet_address: Addr::unchecked("investor"),
+        total_amount: Uint128::new(100),
+        released_amount: Uint128::zero(),
+        pending_amount: Uint128::zero(),
+        early_claimed: false,
+        reward_debt: Uint128::zero(),
+        reward_amount: Uint128::zero(),
+        frozen: false,
+        token_id: Some(String::from("7"))
+    };
+    let set_users = ExecuteMsg::SetUsers{ project_id: Uint128::new(1), stage: Uint128::zero(), user_infos: vec![stray] };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_users).unwrap_err();
+    assert!(matches!(err, ContractError::PositionTokenized{ .. }));
+
+    add_user(deps.as_mut(), 0, "investor", 100);
+    let msg = ExecuteMsg::Tokenize{ project_id: Uint128::new(1), stage: Uint128::zero() };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), msg).unwrap();
+    let transfer = ExecuteMsg::TransferNft{ recipient: String::from("buyer"), token_id: String::from("0") };
+    execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), transfer).unwrap();
+
+    let set_allocation = ExecuteMsg::SetAllocation{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from("investor"),
+        amount: Uint128::zero()
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_allocation).unwrap_err();
+    assert!(matches!(err, ContractError::PositionTokenized{ .. }));
+    let set_users = ExecuteMsg::SetUsers{ project_id: Uint128::new(1), stage: Uint128::zero(), user_infos: Vec::new() };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), set_users).unwrap_err();
+    assert!(matches!(err, ContractError::PositionTokenized{ token_id } if token_id == "0"));
+
+    //a new allocation of the investor goes into a fresh entry
+    add_user(deps.as_mut(), 0, "investor", 30);
+    let msg = QueryMsg::GetUserInfo{ project_id: Uint128::new(1), wallet: String::from("investor") };
+    let user: UserInfo = from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(user.total_amount, Uint128::new(30));
+
+    start_release(deps.as_mut());
+    assert_eq!(pending(deps.as_ref(), "investor"), Uint128::new(30));
+    let claim = ExecuteMsg::ClaimPosition{ project_id: Uint128::new(1), token_id: String::from("0"), recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("buyer", &[]), claim).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("buyer", 100));
+    let claim = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("investor", 30));
+}
+
+#[test]
+fn escrow_migration_and_withdraw(){
+    let mut deps = funded_dependencies(1000);
+    setup_project(deps.as_mut(), unlocked_params(), Uint128::zero());
+    add_user(deps.as_mut(), 0, "investor", 300);
+    add_user(deps.as_mut(), 0, "idle", 200);
+    //a project from before escrows existed
+    ESCROWS.remove(deps.as_mut().storage, 1u128.into());
+
+    migrate(deps.as_mut(), mock_env(), MigrateMsg{ }).unwrap();
+    let escrow = |deps: Deps| -> Uint128 { from_binary(&query(deps, mock_env(),
+        QueryMsg::GetEscrow{ project_id: Uint128::new(1) }).unwrap()).unwrap() };
+    assert_eq!(escrow(deps.as_ref()), Uint128::new(500));
+    start_release(deps.as_mut());
+    let claim = ExecuteMsg::ClaimPendingTokens{ project_id: Uint128::new(1), beneficiary: None, recipient: None };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("investor", &[]), claim).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("investor", 300));
+
+    //only what the allocations don't need can leave
+    let withdraw = |amount: u128| ExecuteMsg::WithdrawEscrow{
+        project_id: Uint128::new(1),
+        amount: Uint128::new(amount),
+        recipient: None
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), withdraw(1)).unwrap_err();
+    assert!(matches!(err, ContractError::NotEnoughBalance{}));
+    let msg = ExecuteMsg::SetAllocation{
+        project_id: Uint128::new(1),
+        stage: Uint128::zero(),
+        wallet: String::from("idle"),
+        amount: Uint128::new(50)
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), msg).unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info("idle", &[]), withdraw(150)).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized{}));
+    let err = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), withdraw(151)).unwrap_err();
+    assert!(matches!(err, ContractError::NotEnoughBalance{}));
+    let res = execute(deps.as_mut(), mock_env(), mock_info("admin", &[]), withdraw(150)).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer("admin", 150));
+    assert_eq!(escrow(deps.as_ref()), Uint128::new(50));
+}